@@ -0,0 +1,82 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use simp_protocol::config::ProtocolConfig;
+use simp_protocol::packet::{Packet, ESCAPE_BYTE, ESCAPE_XOR, START_BYTE};
+
+fn payload() -> Vec<u8> {
+    // 64KB covering every byte value, including the ones that need escaping
+    (0..64 * 1024).map(|i| i as u8).collect()
+}
+
+// Escapes first and checksums in a second pass over the data
+fn two_pass(payload: &[u8], config: &ProtocolConfig) -> (Vec<u8>, u8) {
+    let mut escaped_payload = Vec::new();
+    for &byte in payload {
+        if byte == START_BYTE || byte == config.end_byte || byte == ESCAPE_BYTE {
+            escaped_payload.push(ESCAPE_BYTE);
+            escaped_payload.push(byte ^ ESCAPE_XOR);
+        } else {
+            escaped_payload.push(byte);
+        }
+    }
+    let checksum = Packet::calculate_checksum(payload);
+    (escaped_payload, checksum)
+}
+
+// Escapes and checksums in one pass, pushing onto a vector that grows as needed
+fn unallocated(payload: &[u8], config: &ProtocolConfig) -> (Vec<u8>, u8) {
+    let mut escaped_payload = Vec::new();
+    let mut checksum = 0u8;
+    for &byte in payload {
+        checksum = checksum.wrapping_add(byte);
+        if byte == START_BYTE || byte == config.end_byte || byte == ESCAPE_BYTE {
+            escaped_payload.push(ESCAPE_BYTE);
+            escaped_payload.push(byte ^ ESCAPE_XOR);
+        } else {
+            escaped_payload.push(byte);
+        }
+    }
+    (escaped_payload, checksum)
+}
+
+fn bench_escape(c: &mut Criterion) {
+    let config = ProtocolConfig::default();
+    // 64KB of bytes that never need escaping, for the fast path
+    let plain = vec![0x55; 64 * 1024];
+
+    for (name, payload) in [("escape_64k", payload()), ("escape_64k_plain", plain)] {
+        let mut group = c.benchmark_group(name);
+        group.bench_function("unallocated", |b| {
+            b.iter(|| unallocated(black_box(&payload), black_box(&config)))
+        });
+        group.bench_function("preallocated", |b| {
+            b.iter(|| Packet::escape_payload_with_config(black_box(&payload), black_box(&config)))
+        });
+        group.finish();
+    }
+}
+
+fn bench_new(c: &mut Criterion) {
+    let payload = payload();
+    let config = ProtocolConfig::default();
+    let mut group = c.benchmark_group("packet_new_64k");
+
+    group.bench_function("two_pass", |b| {
+        b.iter_batched(
+            || payload.clone(),
+            |payload| two_pass(black_box(&payload), black_box(&config)),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("single_pass", |b| {
+        b.iter_batched(
+            || payload.clone(),
+            |payload| Packet::new_with_config(black_box(payload), black_box(&config)),
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_new, bench_escape);
+criterion_main!(benches);
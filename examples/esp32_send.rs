@@ -11,6 +11,7 @@ use esp_idf_svc::hal::peripherals::Peripherals;
 use esp_idf_svc::hal::prelude::*;
 use esp_idf_svc::hal::uart::*;
 
+use simp_protocol::error::SimpError;
 use simp_protocol::packet::Packet;
 use simp_protocol::uart::send_packet;
 
@@ -25,10 +26,10 @@ impl<'a> ESPUart<'a> {
 }
 
 impl<'a> simp_protocol::uart::Uart for ESPUart<'a> {
-    fn write(&mut self, data: &[u8]) -> Result<usize, &'static str> {
+    fn write(&mut self, data: &[u8]) -> Result<usize, SimpError> {
         self.uart_driver
             .write(data)
-            .map_err(|_| "Failed to write data")
+            .map_err(|_| SimpError::Uart("Failed to write data"))
     }
 
     fn read(&mut self) -> Option<u8> {
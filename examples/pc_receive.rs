@@ -1,5 +1,9 @@
 use clap::Parser;
-use simp_protocol::uart::receive_packet;
+use simp_protocol::config::ProtocolConfig;
+use simp_protocol::error::SimpError;
+use simp_protocol::tee_uart::TeeUart;
+use simp_protocol::uart::{receive_multiple_packets_with_crc32, receive_packet_with_timeout, Uart};
+use std::fs::File;
 use std::thread::sleep;
 use std::time::Duration;
 
@@ -11,6 +15,12 @@ struct Cli {
     baudrate: u32,
     #[arg(short, long, default_value_t = String::from("COM6"))]
     port: String,
+    /// Write a hex log of all UART traffic to this file
+    #[arg(short, long)]
+    capture: Option<String>,
+    /// Receive a multi-packet transfer and print its CRC-32 instead of the chip info
+    #[arg(short, long)]
+    transfer: bool,
 }
 
 pub struct PCUart {
@@ -26,18 +36,24 @@ impl<'a> PCUart {
     }
 }
 
-impl<'a> simp_protocol::uart::Uart for PCUart {
-    fn write(&mut self, data: &[u8]) -> Result<usize, &'static str> {
+impl simp_protocol::uart::Uart for PCUart {
+    fn write(&mut self, data: &[u8]) -> Result<usize, SimpError> {
         self.serial_port
             .write(data)
-            .map_err(|_| "Failed to write to serial port")
+            .map_err(|_| SimpError::Uart("Failed to write to serial port"))
     }
 
     fn read(&mut self) -> Option<u8> {
+        self.try_read().ok().flatten()
+    }
+
+    fn try_read(&mut self) -> Result<Option<u8>, SimpError> {
         let mut buffer = [0u8; 1];
         match self.serial_port.read(&mut buffer) {
-            Ok(1) => Some(buffer[0]),
-            _ => None,
+            Ok(1) => Ok(Some(buffer[0])),
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => Ok(None),
+            Err(_) => Err(SimpError::Uart("Failed to read from serial port")),
         }
     }
 }
@@ -46,10 +62,28 @@ fn main() {
     let cli = Cli::parse();
     let mut pc_uart = PCUart::new(cli.baudrate, cli.port.as_str());
 
+    match cli.capture {
+        Some(path) => {
+            let capture = File::create(path).expect("Failed to create capture file");
+            receive(&mut TeeUart::new(pc_uart, capture), cli.transfer);
+        }
+        None => receive(&mut pc_uart, cli.transfer),
+    }
+}
+
+fn receive(uart: &mut impl Uart, transfer: bool) {
+    if transfer {
+        receive_transfer(uart);
+    } else {
+        receive_chip_info(uart);
+    }
+}
+
+fn receive_chip_info(uart: &mut impl Uart) {
     println!("Waiting for chip info...");
 
     loop {
-        match receive_packet(&mut pc_uart) {
+        match receive_packet_with_timeout(uart, Duration::from_secs(1)) {
             Ok(packet) => {
                 // Convert the packet payload (Vec<u8>) to a String
                 match String::from_utf8(packet.payload.to_vec()) {
@@ -65,3 +99,21 @@ fn main() {
         }
     }
 }
+
+fn receive_transfer(uart: &mut impl Uart) {
+    println!("Waiting for transfer...");
+
+    loop {
+        match receive_multiple_packets_with_crc32(uart, &ProtocolConfig::default()) {
+            Ok((data, crc)) => {
+                // Compare with the CRC-32 of the file that was sent
+                println!("Received {} bytes, CRC-32: {:08x}", data.len(), crc);
+                break;
+            }
+            Err(e) => {
+                eprintln!("Failed to receive transfer, retrying... Error: {}", e);
+                sleep(Duration::from_millis(100));
+            }
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use crate::config::ProtocolConfig;
+use crate::error::SimpError;
+use crate::packet::Packet;
+use crate::uart::{
+    classify_packet, complete_transfer, push_frame_byte, PacketKind, Termination, ACK_BYTE,
+    NACK_BYTE,
+};
+use futures_core::Stream;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// UART driven by an async runtime such as tokio
+///
+/// Unlike `Uart::read`, `read` waits for the next byte instead of returning None, so no
+/// task spins while the line is idle. Bound waits with `tokio::time::timeout`.
+pub trait AsyncUart {
+    /// Writes the data, returning the number of bytes written.
+    fn write(&mut self, data: &[u8]) -> impl Future<Output = Result<usize, SimpError>> + Send;
+    /// Waits for the next byte, failing on a hard device error or a closed connection.
+    fn read(&mut self) -> impl Future<Output = Result<u8, SimpError>> + Send;
+}
+
+/// Function to send a packet
+pub async fn send_packet(uart: &mut impl AsyncUart, packet: &Packet) -> Result<usize, SimpError> {
+    match uart.write(&packet.try_to_bytes()?).await {
+        Ok(0) => Err(SimpError::WriteStalled),
+        Ok(written) => Ok(written),
+        Err(error) => Err(error),
+    }
+}
+
+/// Function to send a packet and wait for an ACK
+///
+/// Each attempt waits up to `timeout` for the reply, sleeping on the runtime's timer
+/// rather than polling the UART. A NACK triggers the next attempt right away.
+pub async fn send_packet_with_ack(
+    uart: &mut impl AsyncUart,
+    packet: &Packet,
+    retries: usize,
+    timeout: Duration,
+) -> Result<(), SimpError> {
+    for _ in 0..retries {
+        send_packet(uart, packet).await?;
+        match tokio::time::timeout(timeout, wait_for_reply(uart)).await {
+            Ok(Ok(ACK_BYTE)) => return Ok(()),
+            // NACK or timeout, retry sending
+            Ok(Ok(_)) | Err(_) => {}
+            Ok(Err(e)) => return Err(e),
+        }
+    }
+    Err(SimpError::RetriesExhausted)
+}
+
+/// Waits for an ACK or NACK, skipping any other bytes.
+async fn wait_for_reply(uart: &mut impl AsyncUart) -> Result<u8, SimpError> {
+    loop {
+        let response = uart.read().await?;
+        if response == ACK_BYTE || response == NACK_BYTE {
+            return Ok(response);
+        }
+    }
+}
+
+/// Function to receive a packet
+///
+/// Waits until a whole frame has arrived. Wrap it in `tokio::time::timeout` to give up
+/// on a silent line.
+pub async fn receive_packet(uart: &mut impl AsyncUart) -> Result<Packet, SimpError> {
+    receive_packet_with_config(uart, &ProtocolConfig::default()).await
+}
+
+/// Function to receive a packet using the given protocol config
+pub async fn receive_packet_with_config(
+    uart: &mut impl AsyncUart,
+    config: &ProtocolConfig,
+) -> Result<Packet, SimpError> {
+    let mut buffer = Vec::new();
+    loop {
+        let byte = uart.read().await?;
+        if push_frame_byte(&mut buffer, byte, config)? {
+            return Packet::from_bytes_with_config(&buffer, config);
+        }
+    }
+}
+
+/// Function to receive multiple packets
+pub async fn receive_multiple_packets(uart: &mut impl AsyncUart) -> Result<Vec<u8>, SimpError> {
+    receive_multiple_packets_with_config(uart, &ProtocolConfig::default()).await
+}
+
+/// Function to receive multiple packets using the given protocol config
+pub async fn receive_multiple_packets_with_config(
+    uart: &mut impl AsyncUart,
+    config: &ProtocolConfig,
+) -> Result<Vec<u8>, SimpError> {
+    let mut data = Vec::new();
+    let mut received_packets = 0;
+    let mut started = !config.transfer_start;
+
+    let termination = loop {
+        if config
+            .max_packets
+            .is_some_and(|max| received_packets >= max)
+        {
+            break Termination::PacketLimit;
+        }
+
+        let packet = receive_packet_with_config(uart, config).await?;
+        match classify_packet(&packet, received_packets, &mut started, config)? {
+            PacketKind::Skipped => continue,
+            PacketKind::Chunk { data: chunk, last } => {
+                data.extend_from_slice(chunk);
+                received_packets += 1;
+                if last {
+                    break Termination::ShortPacket;
+                }
+            }
+        }
+    };
+    complete_transfer(termination)?;
+    Ok(data)
+}
+
+/// Receive in progress, which hands the UART back along with the result
+type Receiving<U> = Pin<Box<dyn Future<Output = (U, Result<Packet, SimpError>)> + Send>>;
+
+/// Stream of the packets received on an `AsyncUart`
+///
+/// Consume it with `while let Some(packet) = stream.next().await`. A frame that fails to
+/// decode is yielded as an error and the stream carries on with the next frame. The stream
+/// ends once the UART reports `SimpError::ConnectionClosed`.
+pub struct PacketStream<U> {
+    config: ProtocolConfig,
+    uart: Option<U>,
+    receiving: Option<Receiving<U>>,
+}
+
+impl<U: AsyncUart + Send + 'static> PacketStream<U> {
+    /// Creates a stream of the packets received on `uart` using the given protocol config.
+    pub fn new(uart: U, config: ProtocolConfig) -> Self {
+        Self {
+            config,
+            uart: Some(uart),
+            receiving: None,
+        }
+    }
+}
+
+// The UART is only ever moved, never pinned, so the stream can move freely
+impl<U> Unpin for PacketStream<U> {}
+
+impl<U: AsyncUart + Send + 'static> Stream for PacketStream<U> {
+    type Item = Result<Packet, SimpError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.receiving.is_none() {
+            // The stream has ended once the UART isn't handed back
+            let Some(mut uart) = self.uart.take() else {
+                return Poll::Ready(None);
+            };
+            let config = self.config;
+            self.receiving = Some(Box::pin(async move {
+                let result = receive_packet_with_config(&mut uart, &config).await;
+                (uart, result)
+            }));
+        }
+
+        let receiving = self.receiving.as_mut().expect("receive started above");
+        let (uart, result) = std::task::ready!(receiving.as_mut().poll(cx));
+        self.receiving = None;
+        if let Err(SimpError::ConnectionClosed) = result {
+            return Poll::Ready(None);
+        }
+        self.uart = Some(uart);
+        Poll::Ready(Some(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mocks::{multi_packet_stream, MockUart};
+    use futures_util::StreamExt;
+
+    #[tokio::test]
+    async fn test_roundtrip() {
+        let mut uart = MockUart::new();
+        let packet = Packet::new(vec![0x01, crate::packet::END_BYTE, 0x03]);
+        send_packet(&mut uart, &packet).await.unwrap();
+
+        uart.set_read_data(uart.get_written_data());
+        let received = receive_packet(&mut uart).await.unwrap();
+        assert_eq!(received.payload, vec![0x01, crate::packet::END_BYTE, 0x03]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_send_packet_with_ack_retries_on_timeout() {
+        let mut uart = MockUart::new();
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+
+        let started = tokio::time::Instant::now();
+        let result = send_packet_with_ack(&mut uart, &packet, 3, Duration::from_millis(100)).await;
+
+        assert_eq!(result, Err(SimpError::RetriesExhausted));
+        assert_eq!(uart.get_written_data(), packet.to_bytes().repeat(3));
+        // The paused clock only moves while every task sleeps, so nothing busy-waited
+        assert_eq!(started.elapsed(), Duration::from_millis(300));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_send_packet_with_ack_after_nack() {
+        let mut uart = MockUart::new();
+        uart.set_read_data(vec![0x00, NACK_BYTE, ACK_BYTE]);
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+
+        let result = send_packet_with_ack(&mut uart, &packet, 3, Duration::from_millis(100)).await;
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(uart.get_written_data(), packet.to_bytes().repeat(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_receive_packet_waits() {
+        let mut uart = MockUart::new();
+        let result = tokio::time::timeout(Duration::from_secs(1), receive_packet(&mut uart)).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_receive_multiple_packets() {
+        let data: Vec<u8> = (0..600).map(|i| (i % 100) as u8).collect();
+        let stream = multi_packet_stream(&data, &ProtocolConfig::default());
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(stream);
+        assert_eq!(receive_multiple_packets(&mut uart).await.unwrap(), data);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_packet_stream() {
+        let mut corrupted = Packet::new(vec![0x07]).to_bytes();
+        corrupted[2] ^= 0x01;
+        let mut bytes = Packet::new(vec![0x01, 0x02]).to_bytes();
+        bytes.extend(corrupted);
+        bytes.extend(Packet::new(vec![0x03]).to_bytes());
+        let uart = MockUart::new();
+        uart.set_read_data(bytes);
+
+        let mut stream = PacketStream::new(uart, ProtocolConfig::default());
+        assert_eq!(
+            stream.next().await.unwrap().unwrap().payload,
+            vec![0x01, 0x02]
+        );
+        assert_eq!(
+            stream.next().await.unwrap().err(),
+            Some(SimpError::ChecksumMismatch)
+        );
+        assert_eq!(stream.next().await.unwrap().unwrap().payload, vec![0x03]);
+
+        // Nothing more arrives
+        let next = tokio::time::timeout(Duration::from_secs(1), stream.next()).await;
+        assert!(next.is_err());
+    }
+
+    // Async UART whose other end has gone away
+    struct ClosedUart;
+
+    impl AsyncUart for ClosedUart {
+        async fn write(&mut self, _data: &[u8]) -> Result<usize, SimpError> {
+            Err(SimpError::ConnectionClosed)
+        }
+
+        async fn read(&mut self) -> Result<u8, SimpError> {
+            Err(SimpError::ConnectionClosed)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_packet_stream_ends_on_closed_connection() {
+        let mut stream = PacketStream::new(ClosedUart, ProtocolConfig::default());
+        assert!(stream.next().await.is_none());
+        assert!(stream.next().await.is_none());
+    }
+}
@@ -0,0 +1,109 @@
+use crate::error::SimpError;
+
+/// Storage that a multi-packet receive reassembles its data into
+///
+/// `Vec<u8>` grows as needed. `SliceBuffer` writes into caller-provided memory, such as a
+/// static buffer on an embedded target, and fails once it is full, as does
+/// `heapless::Vec` with the `heapless` feature.
+pub trait ReassemblyBuffer {
+    /// Appends a received chunk, failing if it does not fit
+    fn push(&mut self, chunk: &[u8]) -> Result<(), SimpError>;
+
+    /// Number of bytes held
+    fn len(&self) -> usize;
+
+    /// Returns true if no bytes are held
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes held so far
+    fn as_slice(&self) -> &[u8];
+}
+
+#[cfg(feature = "std")]
+impl ReassemblyBuffer for Vec<u8> {
+    fn push(&mut self, chunk: &[u8]) -> Result<(), SimpError> {
+        self.extend_from_slice(chunk);
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        Vec::as_slice(self)
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> ReassemblyBuffer for heapless::Vec<u8, N> {
+    fn push(&mut self, chunk: &[u8]) -> Result<(), SimpError> {
+        self.extend_from_slice(chunk)
+            .map_err(|_| SimpError::BufferFull)
+    }
+
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+}
+
+/// Fixed-capacity buffer backed by a borrowed slice
+pub struct SliceBuffer<'a> {
+    storage: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceBuffer<'a> {
+    /// Creates an empty buffer that can hold up to `storage.len()` bytes
+    pub fn new(storage: &'a mut [u8]) -> Self {
+        SliceBuffer { storage, len: 0 }
+    }
+
+    /// Maximum number of bytes the buffer can hold
+    pub fn capacity(&self) -> usize {
+        self.storage.len()
+    }
+}
+
+impl ReassemblyBuffer for SliceBuffer<'_> {
+    fn push(&mut self, chunk: &[u8]) -> Result<(), SimpError> {
+        let end = self.len + chunk.len();
+        if end > self.storage.len() {
+            return Err(SimpError::BufferFull);
+        }
+        self.storage[self.len..end].copy_from_slice(chunk);
+        self.len = end;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.storage[..self.len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slice_buffer_push() {
+        let mut storage = [0u8; 4];
+        let mut buffer = SliceBuffer::new(&mut storage);
+        buffer.push(&[0x01, 0x02]).unwrap();
+        buffer.push(&[0x03, 0x04]).unwrap();
+
+        assert_eq!(buffer.as_slice(), &[0x01, 0x02, 0x03, 0x04]);
+        assert!(buffer.push(&[0x05]).is_err());
+        assert_eq!(buffer.len(), 4);
+    }
+}
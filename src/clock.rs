@@ -0,0 +1,95 @@
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::{sync::Mutex, thread, time::Instant};
+
+/// Source of time for all timeout logic
+///
+/// Time is measured as a `Duration` since an arbitrary fixed starting point, so
+/// implementations can be backed by a hardware timer as well as by `Instant`. Without the
+/// `std` feature there is no `SystemClock` or `MockClock`, and a clock counting timer ticks
+/// is passed in.
+pub trait Clock {
+    /// Returns the time elapsed since the clock's starting point.
+    fn now(&self) -> Duration;
+    /// Waits for the given duration.
+    fn sleep(&self, duration: Duration);
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        (**self).sleep(duration)
+    }
+}
+
+/// Clock backed by the system's monotonic clock
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    start_time: Instant,
+}
+
+#[cfg(feature = "std")]
+impl SystemClock {
+    /// Creates a new system clock starting at the current instant.
+    pub fn new() -> Self {
+        Self {
+            start_time: Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Clock that only moves when told to, for deterministic tests
+///
+/// Sleeping advances the clock by the requested duration instead of blocking.
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct MockClock {
+    // Behind a mutex so a mock UART on another task can share the clock
+    now: Mutex<Duration>,
+}
+
+#[cfg(feature = "std")]
+impl MockClock {
+    /// Creates a new mock clock at time zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the clock forward by the given duration.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for MockClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
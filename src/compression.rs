@@ -0,0 +1,199 @@
+use crate::config::ProtocolConfig;
+use crate::error::SimpError;
+use crate::uart::{receive_multiple_packets_with_config, send_multiple_packets_with_config, Uart};
+use std::time::Duration;
+
+/// Bit of the transfer header set when the data that follows it is compressed
+pub const TRANSFER_COMPRESSED: u8 = 0x01;
+
+/// Longest run or literal a single PackBits header byte can describe
+const MAX_RUN: usize = 128;
+
+/// Compresses data with PackBits run-length encoding
+///
+/// Runs of three or more equal bytes become a header byte and the repeated byte, other
+/// bytes are copied in literals of up to 128 bytes behind a header byte. Data without
+/// runs grows by one byte in 128, so the sender only uses it when it pays off.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    let mut literal_start = 0;
+    let mut index = 0;
+    while index < data.len() {
+        let run = data[index..]
+            .iter()
+            .take(MAX_RUN)
+            .take_while(|&&byte| byte == data[index])
+            .count();
+        if run < 3 {
+            index += run;
+            if index - literal_start >= MAX_RUN {
+                push_literal(
+                    &mut compressed,
+                    &data[literal_start..literal_start + MAX_RUN],
+                );
+                literal_start += MAX_RUN;
+            }
+            continue;
+        }
+
+        push_literal(&mut compressed, &data[literal_start..index]);
+        compressed.push((1 - run as i16) as u8);
+        compressed.push(data[index]);
+        index += run;
+        literal_start = index;
+    }
+    push_literal(&mut compressed, &data[literal_start..]);
+    compressed
+}
+
+fn push_literal(compressed: &mut Vec<u8>, literal: &[u8]) {
+    for chunk in literal.chunks(MAX_RUN) {
+        compressed.push(chunk.len() as u8 - 1);
+        compressed.extend_from_slice(chunk);
+    }
+}
+
+/// Decompresses data produced by `compress`
+///
+/// Fails with `SimpError::InvalidCompressedData` if a literal or run is cut short.
+pub fn decompress(compressed: &[u8]) -> Result<Vec<u8>, SimpError> {
+    let mut data = Vec::new();
+    let mut remaining = compressed;
+    while let Some((&header, rest)) = remaining.split_first() {
+        let header = header as i8;
+        remaining = match header {
+            0.. => {
+                let len = header as usize + 1;
+                if rest.len() < len {
+                    return Err(SimpError::InvalidCompressedData);
+                }
+                let (literal, rest) = rest.split_at(len);
+                data.extend_from_slice(literal);
+                rest
+            }
+            // No-op header, skipped as PackBits decoders do
+            -128 => rest,
+            _ => {
+                let (&byte, rest) = rest.split_first().ok_or(SimpError::InvalidCompressedData)?;
+                data.resize(data.len() + (1 - header as isize) as usize, byte);
+                rest
+            }
+        };
+    }
+    Ok(data)
+}
+
+/// Function to send multiple packets, compressing the data when that makes it smaller
+///
+/// The transfer starts with a header byte whose `TRANSFER_COMPRESSED` bit tells the
+/// receiver whether the data after it is compressed. Data that doesn't shrink, such as
+/// data that is already compressed or encrypted, is sent as is. Both ends must agree to
+/// use the header.
+pub fn send_multiple_packets_with_compression(
+    uart: &mut impl Uart,
+    data: &[u8],
+    retries: usize,
+    timeout: Duration,
+    config: &ProtocolConfig,
+) -> Result<(), SimpError> {
+    let compressed = compress(data);
+    let transfer = if compressed.len() < data.len() {
+        [&[TRANSFER_COMPRESSED], compressed.as_slice()].concat()
+    } else {
+        [&[0], data].concat()
+    };
+    send_multiple_packets_with_config(uart, &transfer, retries, timeout, config)
+}
+
+/// Function to receive multiple packets sent with `send_multiple_packets_with_compression`
+///
+/// Fails with `SimpError::InvalidCompressedData` if the header is missing or has
+/// unknown bits set.
+pub fn receive_multiple_packets_with_compression(
+    uart: &mut impl Uart,
+    config: &ProtocolConfig,
+) -> Result<Vec<u8>, SimpError> {
+    let transfer = receive_multiple_packets_with_config(uart, config)?;
+    match transfer.split_first() {
+        Some((&0, data)) => Ok(data.to_vec()),
+        Some((&TRANSFER_COMPRESSED, compressed)) => decompress(compressed),
+        _ => Err(SimpError::InvalidCompressedData),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mocks::MockUart;
+    use crate::uart::ACK_BYTE;
+
+    /// Sends the data with compression and receives it back, returning the header byte
+    /// and the received data.
+    fn transfer(data: &[u8]) -> (u8, Vec<u8>) {
+        let config = ProtocolConfig::default();
+        let mut sender = MockUart::new();
+        sender.set_read_data(vec![ACK_BYTE; 8]);
+        send_multiple_packets_with_compression(
+            &mut sender,
+            data,
+            3,
+            Duration::from_millis(100),
+            &config,
+        )
+        .unwrap();
+
+        // Start byte, length, and sequence number come before the header
+        let sent = sender.get_written_data();
+        let mut receiver = MockUart::new();
+        receiver.set_read_data(sent.clone());
+        let received = receive_multiple_packets_with_compression(&mut receiver, &config).unwrap();
+        (sent[3], received)
+    }
+
+    #[test]
+    fn test_compressed_transfer() {
+        let mut data = vec![0x00; 500];
+        data.extend(b"header");
+        data.extend([0xFF; 300]);
+
+        let (header, received) = transfer(&data);
+        assert_eq!(header, TRANSFER_COMPRESSED);
+        assert_eq!(received, data);
+    }
+
+    #[test]
+    fn test_raw_transfer() {
+        // No runs, so compressing would only add overhead
+        let data: Vec<u8> = (0..600).map(|i| (i * 7 % 251) as u8).collect();
+        assert!(compress(&data).len() >= data.len());
+
+        let (header, received) = transfer(&data);
+        assert_eq!(header, 0);
+        assert_eq!(received, data);
+    }
+
+    #[test]
+    fn test_compress_roundtrip() {
+        let cases: [&[u8]; 6] = [
+            &[],
+            &[0x01],
+            &[0x01, 0x01],
+            &[0x01, 0x01, 0x01],
+            &[0x05; 1000],
+            &[0x01, 0x02, 0x02, 0x02, 0x03, 0x04, 0x04],
+        ];
+        for data in cases {
+            assert_eq!(decompress(&compress(data)).unwrap(), data);
+        }
+        assert_eq!(compress(&[0x05; 200]), vec![0x81, 0x05, 0xB9, 0x05]);
+    }
+
+    #[test]
+    fn test_decompress_truncated() {
+        assert_eq!(
+            decompress(&[0x02, 0x01]),
+            Err(SimpError::InvalidCompressedData)
+        );
+        assert_eq!(decompress(&[0xFE]), Err(SimpError::InvalidCompressedData));
+    }
+}
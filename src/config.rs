@@ -0,0 +1,138 @@
+use crate::packet::{crc16, ChecksumKind, ChecksumMode, Framing, END_BYTE, START_BYTE};
+use crate::uart::MAX_PAYLOAD_SIZE;
+use core::time::Duration;
+
+/// Protocol settings used by the send and receive functions
+///
+/// Framing and checksum settings must match on both ends of a link. The limits only
+/// apply to the receiving end and the pacing only to the sending end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolConfig {
+    /// Bytes covered by the packet checksum
+    pub checksum_mode: ChecksumMode,
+    /// Algorithm used to compute the packet checksum
+    pub checksum_kind: ChecksumKind,
+    /// How frames are delimited on the wire
+    pub framing: Framing,
+    /// Byte that begins a frame
+    ///
+    /// Like the end byte, it is escaped inside the payload. Legacy peers often use STX
+    /// (0x02) and ETX (0x03) instead of the defaults.
+    pub start_byte: u8,
+    /// Byte that terminates a frame
+    ///
+    /// It is escaped inside the payload, so it never shows up before the end of a frame.
+    pub end_byte: u8,
+    /// Width of the sequence number in multi-packet transfers
+    pub sequence_width: SequenceWidth,
+    /// Largest payload of a packet in a multi-packet transfer, sequence number included
+    ///
+    /// Lower it for links with a small MTU. The receiver takes the first packet shorter
+    /// than this for the last one, so both ends must agree on it; the config handshake
+    /// checks that they do.
+    pub max_payload_size: usize,
+    /// Maximum number of packets accepted in one multi-packet transfer, if any
+    ///
+    /// Guards against a stuck or malicious sender that never sends a short final packet.
+    pub max_packets: Option<usize>,
+    /// Maximum number of bytes buffered while waiting for the end byte, if any
+    ///
+    /// Guards against unbounded buffer growth on a babbling line that never terminates a
+    /// frame. A valid frame is never longer than 4 bytes plus twice the largest payload.
+    pub max_frame_size: Option<usize>,
+    /// Whether multi-packet transfers begin with a transfer-start marker frame
+    ///
+    /// When set, the sender leads with the marker and the receiver discards frames until
+    /// it arrives, so stray frames from an aborted transfer are not mistaken for data.
+    pub transfer_start: bool,
+    /// Pause between the packets of a multi-packet transfer
+    ///
+    /// Gives slow receivers time to process a packet before the next one arrives. It is
+    /// applied after each reply, but not after the last packet.
+    pub inter_packet_delay: Duration,
+}
+
+impl Default for ProtocolConfig {
+    fn default() -> Self {
+        ProtocolConfig {
+            checksum_mode: ChecksumMode::default(),
+            checksum_kind: ChecksumKind::default(),
+            framing: Framing::default(),
+            start_byte: START_BYTE,
+            end_byte: END_BYTE,
+            sequence_width: SequenceWidth::default(),
+            max_payload_size: MAX_PAYLOAD_SIZE,
+            max_packets: None,
+            max_frame_size: None,
+            transfer_start: false,
+            inter_packet_delay: Duration::ZERO,
+        }
+    }
+}
+
+impl ProtocolConfig {
+    /// Returns a hash of the settings that must match on both ends of a link.
+    ///
+    /// The receive limits and the sender's pacing are left out, since each end is free to
+    /// choose its own. Compared by the config handshake in the `handshake` module.
+    pub fn fingerprint(&self) -> u16 {
+        let settings = [
+            self.checksum_mode as u8,
+            self.checksum_kind as u8,
+            self.framing as u8,
+            self.start_byte,
+            self.end_byte,
+            self.sequence_width as u8,
+            self.transfer_start as u8,
+        ];
+        let max_payload_size = (self.max_payload_size as u32).to_le_bytes();
+        crc16(settings.into_iter().chain(max_payload_size))
+    }
+}
+
+/// Width of the sequence number prefixed to each packet of a multi-packet transfer
+///
+/// A one byte sequence number wraps after 256 packets. Use two bytes for transfers
+/// with more chunks than that to avoid wraparound ambiguity.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceWidth {
+    /// One byte sequence number
+    #[default]
+    One,
+    /// Two byte little-endian sequence number
+    Two,
+}
+
+impl SequenceWidth {
+    /// Returns the number of bytes the sequence number occupies.
+    pub fn bytes(self) -> usize {
+        match self {
+            SequenceWidth::One => 1,
+            SequenceWidth::Two => 2,
+        }
+    }
+
+    /// Encodes the sequence number of the packet at the given index, wrapping on overflow.
+    #[cfg(feature = "std")]
+    pub fn encode(self, index: usize) -> Vec<u8> {
+        match self {
+            SequenceWidth::One => vec![index as u8],
+            SequenceWidth::Two => (index as u16).to_le_bytes().to_vec(),
+        }
+    }
+
+    /// Decodes a sequence number written by `encode`.
+    pub fn decode(sequence: &[u8]) -> usize {
+        sequence
+            .iter()
+            .rev()
+            .fold(0, |acc, &byte| (acc << 8) | byte as usize)
+    }
+
+    /// Returns how many packets back from `index` the most recent packet with the given
+    /// sequence number is.
+    pub fn distance(self, sequence: usize, index: usize) -> usize {
+        let modulus = 1 << (8 * self.bytes());
+        (index % modulus + modulus - sequence % modulus) % modulus
+    }
+}
@@ -0,0 +1,114 @@
+use crate::error::SimpError;
+use crate::uart::Uart;
+use crossbeam_channel::{never, unbounded, Receiver, Sender, TryRecvError};
+use std::collections::VecDeque;
+
+/// UART endpoint on a simulated bus backed by crossbeam channels
+///
+/// Endpoints come in connected pairs: bytes written to one can be read from the other.
+/// `writer` attaches another device to the same side of the bus, so several threads can
+/// write to one reader, as on a multi-drop line. Each `write` is delivered as a unit, so
+/// concurrent writes never interleave within a frame.
+pub struct CrossbeamUart {
+    tx: Sender<Vec<u8>>,
+    rx: Receiver<Vec<u8>>,
+    pending: VecDeque<u8>,
+}
+
+impl CrossbeamUart {
+    /// Creates a pair of connected endpoints.
+    pub fn pair() -> (Self, Self) {
+        let (a_tx, b_rx) = unbounded();
+        let (b_tx, a_rx) = unbounded();
+        let a = CrossbeamUart {
+            tx: a_tx,
+            rx: a_rx,
+            pending: VecDeque::new(),
+        };
+        let b = CrossbeamUart {
+            tx: b_tx,
+            rx: b_rx,
+            pending: VecDeque::new(),
+        };
+        (a, b)
+    }
+
+    /// Creates another endpoint that writes to the same reader as this one.
+    ///
+    /// The new endpoint only writes: its reads never return data, so it does not take
+    /// bytes meant for this endpoint.
+    pub fn writer(&self) -> Self {
+        CrossbeamUart {
+            tx: self.tx.clone(),
+            rx: never(),
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl Uart for CrossbeamUart {
+    fn write(&mut self, data: &[u8]) -> Result<usize, SimpError> {
+        self.tx
+            .send(data.to_vec())
+            .map_err(|_| SimpError::ConnectionClosed)?;
+        Ok(data.len())
+    }
+
+    fn read(&mut self) -> Option<u8> {
+        self.try_read().ok().flatten()
+    }
+
+    /// Fails with `SimpError::ConnectionClosed` once the other end has been dropped and
+    /// everything it sent has been read.
+    fn try_read(&mut self) -> Result<Option<u8>, SimpError> {
+        if self.pending.is_empty() {
+            match self.rx.try_recv() {
+                Ok(data) => self.pending.extend(data),
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => return Err(SimpError::ConnectionClosed),
+            }
+        }
+        Ok(self.pending.pop_front())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::Packet;
+    use crate::uart::{receive_packet, send_packet};
+    use std::thread;
+
+    #[test]
+    fn test_two_writers_one_reader() {
+        let (writer, mut reader) = CrossbeamUart::pair();
+
+        for payload in [vec![0x01, 0x02, 0x03], vec![0x04, 0x05]] {
+            let mut writer = writer.writer();
+            thread::spawn(move || send_packet(&mut writer, &Packet::new(payload)).unwrap())
+                .join()
+                .unwrap();
+        }
+
+        let first = receive_packet(&mut reader).unwrap();
+        let second = receive_packet(&mut reader).unwrap();
+        assert_eq!(first.payload, vec![0x01, 0x02, 0x03]);
+        assert_eq!(second.payload, vec![0x04, 0x05]);
+        assert!(reader.read().is_none());
+    }
+
+    #[test]
+    fn test_writer_does_not_read() {
+        let (mut a, mut b) = CrossbeamUart::pair();
+        b.write(&[0x01, 0x02]).unwrap();
+        assert_eq!(a.read(), Some(0x01));
+
+        // The pending byte stays with the endpoint that received it
+        let mut writer = a.writer();
+        assert_eq!(writer.read(), None);
+        assert_eq!(a.read(), Some(0x02));
+
+        writer.write(&[0x03]).unwrap();
+        assert_eq!(b.read(), Some(0x03));
+    }
+}
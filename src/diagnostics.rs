@@ -0,0 +1,287 @@
+use crate::config::ProtocolConfig;
+use crate::packet::Packet;
+
+/// Minimum number of sampled bytes required before the heuristic gives a verdict.
+pub const MIN_SAMPLE_SIZE: usize = 64;
+
+/// Highest error rate at which a link is still classified as good.
+pub const GOOD_ERROR_RATE: f64 = 0.01;
+
+/// Highest error rate at which a link is classified as marginal rather than bad.
+pub const MARGINAL_ERROR_RATE: f64 = 0.1;
+
+/// Summary of how a sample of received bytes frames up
+///
+/// A link running at the wrong baud rate turns every frame into garbage: start bytes
+/// become rare and the few frames that do appear fail their structure or checksum checks.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FramingReport {
+    /// Number of bytes in the sample
+    pub sampled_bytes: usize,
+    /// Number of start byte occurrences in the sample
+    pub start_bytes: usize,
+    /// Frames that decoded successfully
+    pub valid_frames: usize,
+    /// Frames that failed the structure or checksum checks
+    pub invalid_frames: usize,
+}
+
+impl FramingReport {
+    /// Analyzes a sample of received bytes and counts valid and invalid frames.
+    pub fn analyze(sample: &[u8]) -> Self {
+        Self::analyze_with_config(sample, &ProtocolConfig::default())
+    }
+
+    /// Analyzes a sample of received bytes framed according to the given protocol config.
+    ///
+    /// Escaped frames are found by their start and end bytes. A `Framing::LengthPrefixed`
+    /// frame has no end byte, so its length field is walked instead.
+    pub fn analyze_with_config(sample: &[u8], config: &ProtocolConfig) -> Self {
+        if !config.framing.is_escaped() {
+            return Self::analyze_length_prefixed(sample, config);
+        }
+        let mut report = FramingReport {
+            sampled_bytes: sample.len(),
+            ..Default::default()
+        };
+        let mut frame: Option<Vec<u8>> = None;
+
+        for &byte in sample {
+            if byte == config.start_byte {
+                report.start_bytes += 1;
+                if frame.is_some() {
+                    // A new frame started before the previous one ended
+                    report.invalid_frames += 1;
+                }
+                frame = Some(vec![byte]);
+                continue;
+            }
+
+            if let Some(buffer) = frame.as_mut() {
+                buffer.push(byte);
+                if byte == config.end_byte {
+                    match Packet::from_bytes_with_config(buffer, config) {
+                        Ok(_) => report.valid_frames += 1,
+                        Err(_) => report.invalid_frames += 1,
+                    }
+                    frame = None;
+                }
+            }
+        }
+
+        report
+    }
+
+    /// Walks a sample of length-prefixed frames by their length fields.
+    ///
+    /// A frame that fails to decode may have started at a payload byte that happens to
+    /// equal the start byte, so the walk resumes one byte after it rather than after its
+    /// declared length. A length field above `max_payload_size` counts as an invalid
+    /// frame, while a plausible frame cut off by the end of the sample is not counted.
+    fn analyze_length_prefixed(sample: &[u8], config: &ProtocolConfig) -> Self {
+        let mut report = FramingReport {
+            sampled_bytes: sample.len(),
+            start_bytes: sample
+                .iter()
+                .filter(|&&byte| byte == config.start_byte)
+                .count(),
+            ..Default::default()
+        };
+        let mut position = 0;
+
+        while position < sample.len() {
+            if sample[position] != config.start_byte {
+                position += 1;
+                continue;
+            }
+            let rest = &sample[position..];
+            let Some(frame_len) = Packet::frame_len(rest, config) else {
+                break;
+            };
+            let overhead = config.framing.overhead(config.checksum_kind);
+            if frame_len - overhead > config.max_payload_size {
+                report.invalid_frames += 1;
+                position += 1;
+                continue;
+            }
+            if frame_len > rest.len() {
+                break;
+            }
+            match Packet::from_bytes_with_config(&rest[..frame_len], config) {
+                Ok(_) => {
+                    report.valid_frames += 1;
+                    position += frame_len;
+                }
+                Err(_) => {
+                    report.invalid_frames += 1;
+                    position += 1;
+                }
+            }
+        }
+
+        report
+    }
+
+    /// Returns true if the sample looks like traffic received at the wrong baud rate.
+    ///
+    /// The sample is flagged when it is large enough to judge and either contains no
+    /// start byte at all or most of its frames fail to decode.
+    pub fn is_probable_baud_mismatch(&self) -> bool {
+        if self.sampled_bytes < MIN_SAMPLE_SIZE {
+            return false;
+        }
+        if self.start_bytes == 0 {
+            return true;
+        }
+        self.invalid_frames > self.valid_frames
+    }
+}
+
+/// Function to check a sample of received bytes for baud-rate mismatch symptoms
+pub fn detect_baud_mismatch(sample: &[u8]) -> bool {
+    FramingReport::analyze(sample).is_probable_baud_mismatch()
+}
+
+/// Transfer counters collected by the caller over the life of a link
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// Packets sent, including retransmissions
+    pub packets_sent: usize,
+    /// NACKs received in reply
+    pub nacks: usize,
+    /// Received frames that failed their checksum
+    pub checksum_errors: usize,
+}
+
+/// Rough classification of a link by its error rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkQuality {
+    Good,
+    Marginal,
+    Bad,
+}
+
+impl Stats {
+    /// Returns the share of sent packets that ended in a NACK or a checksum error.
+    ///
+    /// A link with no packets sent yet has an error rate of 0.
+    pub fn error_rate(&self) -> f64 {
+        if self.packets_sent == 0 {
+            return 0.0;
+        }
+        (self.nacks + self.checksum_errors) as f64 / self.packets_sent as f64
+    }
+
+    /// Classifies the link using `GOOD_ERROR_RATE` and `MARGINAL_ERROR_RATE`.
+    pub fn link_quality(&self) -> LinkQuality {
+        let error_rate = self.error_rate();
+        if error_rate <= GOOD_ERROR_RATE {
+            LinkQuality::Good
+        } else if error_rate <= MARGINAL_ERROR_RATE {
+            LinkQuality::Marginal
+        } else {
+            LinkQuality::Bad
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::Framing;
+
+    fn noise(len: usize) -> Vec<u8> {
+        // Simple xorshift generator so the test stays deterministic
+        let mut state = 0x2545_f491u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_random_noise_is_flagged() {
+        let sample = noise(4096);
+        let report = FramingReport::analyze(&sample);
+
+        assert!(report.invalid_frames > report.valid_frames);
+        assert!(report.is_probable_baud_mismatch());
+        assert!(detect_baud_mismatch(&sample));
+    }
+
+    #[test]
+    fn test_valid_traffic_is_not_flagged() {
+        let mut sample = Vec::new();
+        for i in 0..32u8 {
+            sample.extend(Packet::new(vec![i, 0x01, 0x02, 0x03]).to_bytes());
+        }
+
+        let report = FramingReport::analyze(&sample);
+        assert_eq!(report.valid_frames, 32);
+        assert_eq!(report.invalid_frames, 0);
+        assert!(!report.is_probable_baud_mismatch());
+    }
+
+    #[test]
+    fn test_length_prefixed_traffic() {
+        let config = ProtocolConfig {
+            framing: Framing::LengthPrefixed,
+            ..Default::default()
+        };
+        // Payloads full of start and end bytes, which this framing sends raw
+        let mut sample = Vec::new();
+        for i in 0..32u8 {
+            let payload = vec![config.start_byte, i, config.end_byte, config.start_byte];
+            sample.extend(Packet::new_with_config(payload, &config).to_bytes());
+        }
+
+        let report = FramingReport::analyze_with_config(&sample, &config);
+        assert_eq!(report.valid_frames, 32);
+        assert_eq!(report.invalid_frames, 0);
+        assert!(!report.is_probable_baud_mismatch());
+
+        let report = FramingReport::analyze_with_config(&noise(4096), &config);
+        assert!(report.is_probable_baud_mismatch());
+    }
+
+    #[test]
+    fn test_sample_without_start_byte_is_flagged() {
+        let sample = vec![0x55; MIN_SAMPLE_SIZE];
+        assert!(detect_baud_mismatch(&sample));
+    }
+
+    #[test]
+    fn test_short_sample_is_not_flagged() {
+        let sample = vec![0x55; MIN_SAMPLE_SIZE - 1];
+        assert!(!detect_baud_mismatch(&sample));
+    }
+
+    #[test]
+    fn test_error_rate() {
+        let stats = Stats {
+            packets_sent: 200,
+            nacks: 3,
+            checksum_errors: 2,
+        };
+        assert_eq!(stats.error_rate(), 0.025);
+        assert_eq!(Stats::default().error_rate(), 0.0);
+    }
+
+    #[test]
+    fn test_link_quality_boundaries() {
+        let stats = |errors| Stats {
+            packets_sent: 100,
+            nacks: errors,
+            checksum_errors: 0,
+        };
+        assert_eq!(Stats::default().link_quality(), LinkQuality::Good);
+        assert_eq!(stats(1).link_quality(), LinkQuality::Good);
+        assert_eq!(stats(2).link_quality(), LinkQuality::Marginal);
+        assert_eq!(stats(10).link_quality(), LinkQuality::Marginal);
+        assert_eq!(stats(11).link_quality(), LinkQuality::Bad);
+    }
+}
@@ -0,0 +1,114 @@
+use crate::packet::LengthMismatch;
+use core::fmt;
+
+/// Error returned by the packet, send, and receive functions
+///
+/// Errors reported by a `Uart`, `Reader`, or `Writer` implementation are passed on as
+/// `Uart` with the implementation's message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimpError {
+    /// Frame does not start with the start byte, end with the end byte, or is too short
+    InvalidStructure,
+    /// Length field disagrees with the payload found in the frame
+    LengthMismatch(LengthMismatch),
+    /// Checksum does not match the payload
+    ChecksumMismatch,
+    /// Frame ends before the header could be read
+    FrameTooShort,
+    /// Frame has an empty payload where data was expected
+    EmptyPacket,
+    /// More than `max_frame_size` bytes arrived without a complete frame
+    FrameTooLarge,
+    /// Escaped payload does not fit in the length field
+    PayloadTooLarge,
+    /// The UART accepted none of the bytes written to it
+    WriteStalled,
+    /// The UART failed to write a packet, for implementations with no message of their own
+    WriteFailed,
+    /// The UART failed to write an ACK or NACK reply
+    ReplyFailed,
+    /// No complete frame arrived before the UART ran out of data
+    Timeout,
+    /// No ACK arrived for any attempt
+    RetriesExhausted,
+    /// The send deadline passed before the packet was acknowledged
+    DeadlineExceeded,
+    /// The receiver asked to resend a packet that was never sent
+    InvalidResendRequest,
+    /// A multi-packet transfer skipped or repeated a sequence number
+    SequenceOutOfOrder,
+    /// A multi-packet transfer went past `max_packets`
+    TooManyPackets,
+    /// The protocol config cannot be used, such as a max payload size with no room for data
+    InvalidConfig,
+    /// The reassembly buffer has no room for the next chunk
+    BufferFull,
+    /// Received data could not be written to the output
+    OutputFailed,
+    /// The other end of a config handshake uses a different config
+    ConfigMismatch,
+    /// A config handshake frame did not carry a fingerprint
+    InvalidHandshake,
+    /// A compressed transfer has an unknown header or is cut short
+    InvalidCompressedData,
+    /// Metadata key, value, or block is too long to encode
+    MetadataTooLarge,
+    /// Metadata block is truncated or has a key that isn't UTF-8
+    InvalidMetadata,
+    /// The other end closed the connection, so no more data will arrive
+    ConnectionClosed,
+    /// Error reported by the UART implementation
+    Uart(&'static str),
+}
+
+impl fmt::Display for SimpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            SimpError::InvalidStructure => "Invalid packet structure",
+            SimpError::LengthMismatch(_) => "Length mismatch",
+            SimpError::ChecksumMismatch => "Checksum mismatch",
+            SimpError::FrameTooShort => "Frame too short",
+            SimpError::EmptyPacket => "Empty packet received",
+            SimpError::FrameTooLarge => "Frame too large",
+            SimpError::PayloadTooLarge => "Escaped payload too large",
+            SimpError::WriteStalled => "Write stalled",
+            SimpError::WriteFailed => "Failed to send packet",
+            SimpError::ReplyFailed => "Failed to send reply",
+            SimpError::Timeout => "Failed to receive packet",
+            SimpError::RetriesExhausted => "Failed to send packet after retries",
+            SimpError::DeadlineExceeded => "Send deadline exceeded",
+            SimpError::InvalidResendRequest => "Invalid resend request",
+            SimpError::SequenceOutOfOrder => "Packet sequence out of order",
+            SimpError::TooManyPackets => "Too many packets received",
+            SimpError::InvalidConfig => "Invalid protocol config",
+            SimpError::BufferFull => "Reassembly buffer full",
+            SimpError::OutputFailed => "Failed to write received data",
+            SimpError::ConfigMismatch => "Config mismatch",
+            SimpError::InvalidHandshake => "Invalid handshake",
+            SimpError::InvalidCompressedData => "Invalid compressed data",
+            SimpError::MetadataTooLarge => "Metadata too large",
+            SimpError::InvalidMetadata => "Invalid metadata",
+            SimpError::ConnectionClosed => "Connection closed",
+            SimpError::Uart(message) => message,
+        };
+        f.write_str(message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SimpError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_keeps_messages() {
+        assert_eq!(SimpError::ChecksumMismatch.to_string(), "Checksum mismatch");
+        assert_eq!(SimpError::Timeout.to_string(), "Failed to receive packet");
+        assert_eq!(
+            SimpError::Uart("Device unplugged").to_string(),
+            "Device unplugged"
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use crate::clock::Clock;
+use crate::config::ProtocolConfig;
+use crate::error::SimpError;
+use crate::packet::{
+    checksum_of_iter, split_frame, unescaped, verify_frame, ChecksumMode, ESCAPE_BYTE, ESCAPE_XOR,
+};
+use crate::uart::{push_frame_byte, Uart};
+use core::time::Duration;
+use heapless::Vec;
+
+/// Encodes a frame carrying the given payload into a buffer that holds up to `N` bytes
+///
+/// Produces the same bytes as `Packet::new_with_config(..).to_bytes()` without
+/// allocating. Fails with `SimpError::PayloadTooLarge` if the escaped payload does not fit
+/// the length field and with `SimpError::BufferFull` if the frame does not fit in `N`.
+pub fn encode_frame<const N: usize>(
+    payload: &[u8],
+    config: &ProtocolConfig,
+) -> Result<Vec<u8, N>, SimpError> {
+    let length_width = config.framing.length_width();
+    let mut frame = Vec::new();
+    // The length field is filled in once the escaped payload is in place
+    extend(&mut frame, &[config.start_byte, 0, 0][..1 + length_width])?;
+    let header_len = frame.len();
+    for &byte in payload {
+        push_escaped(&mut frame, byte, config)?;
+    }
+
+    let length = frame.len() - header_len;
+    if length > config.framing.max_payload() {
+        return Err(SimpError::PayloadTooLarge);
+    }
+    frame[1..header_len].copy_from_slice(&(length as u16).to_le_bytes()[..length_width]);
+    let checksum = match config.checksum_mode {
+        ChecksumMode::Payload => checksum_of_iter(payload.iter().copied(), config.checksum_kind),
+        ChecksumMode::EscapedPayload => {
+            checksum_of_iter(frame[header_len..].iter().copied(), config.checksum_kind)
+        }
+    };
+    let checksum_width = config.checksum_kind.width();
+    for &byte in &checksum.to_be_bytes()[2 - checksum_width..] {
+        push_escaped(&mut frame, byte, config)?;
+    }
+    if config.framing.is_escaped() {
+        extend(&mut frame, &[config.end_byte])?;
+    }
+    Ok(frame)
+}
+
+/// Decodes the payload of a frame into a buffer that holds up to `N` bytes
+///
+/// The frame is verified in place before anything is copied. Fails with
+/// `SimpError::BufferFull` if the payload does not fit in `N`.
+pub fn decode_frame<const N: usize>(
+    frame: &[u8],
+    config: &ProtocolConfig,
+) -> Result<Vec<u8, N>, SimpError> {
+    verify_frame(frame, config)?;
+    let (payload, _) = split_frame(frame, config);
+    let mut decoded = Vec::new();
+    if config.framing.is_escaped() {
+        for byte in unescaped(payload) {
+            extend(&mut decoded, &[byte])?;
+        }
+    } else {
+        extend(&mut decoded, payload)?;
+    }
+    Ok(decoded)
+}
+
+/// Function to send a frame carrying the given payload, encoded in a buffer of `N` bytes
+///
+/// Behaves like `send_packet`, failing with `SimpError::WriteStalled` if the UART takes
+/// none of the bytes.
+pub fn send_frame<const N: usize>(
+    uart: &mut impl Uart,
+    payload: &[u8],
+    config: &ProtocolConfig,
+) -> Result<usize, SimpError> {
+    let frame = encode_frame::<N>(payload, config)?;
+    match uart.write(&frame) {
+        Ok(0) => Err(SimpError::WriteStalled),
+        Ok(written) => Ok(written),
+        Err(error) => Err(error),
+    }
+}
+
+/// Function to receive a frame within a timeout and decode its payload
+///
+/// Behaves like `receive_packet_with_timeout_and_clock`, with the whole frame assembled
+/// in a buffer of `N` bytes. Fails with `SimpError::FrameTooLarge` once the frame no
+/// longer fits. Without the `std` feature, pass a clock backed by a hardware timer.
+pub fn receive_frame<const N: usize>(
+    uart: &mut impl Uart,
+    timeout: Duration,
+    config: &ProtocolConfig,
+    clock: &impl Clock,
+) -> Result<Vec<u8, N>, SimpError> {
+    let mut frame = Vec::<u8, N>::new();
+    let start_time = clock.now();
+    while clock.now() - start_time < timeout {
+        if let Some(byte) = uart.try_read()? {
+            if push_frame_byte(&mut frame, byte, config)? {
+                return decode_frame(&frame, config);
+            }
+        }
+    }
+    Err(SimpError::Timeout)
+}
+
+/// Appends a byte to the frame, escaped if the framing calls for it.
+fn push_escaped<const N: usize>(
+    frame: &mut Vec<u8, N>,
+    byte: u8,
+    config: &ProtocolConfig,
+) -> Result<(), SimpError> {
+    if config.framing.is_escaped()
+        && (byte == config.start_byte || byte == config.end_byte || byte == ESCAPE_BYTE)
+    {
+        extend(frame, &[ESCAPE_BYTE, byte ^ ESCAPE_XOR])
+    } else {
+        extend(frame, &[byte])
+    }
+}
+
+fn extend<const N: usize>(buffer: &mut Vec<u8, N>, bytes: &[u8]) -> Result<(), SimpError> {
+    buffer
+        .extend_from_slice(bytes)
+        .map_err(|_| SimpError::BufferFull)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::mocks::MockUart;
+    use crate::packet::{ChecksumKind, Framing, Packet, END_BYTE, START_BYTE};
+
+    #[test]
+    fn test_frames_match_packets() {
+        let payload = [0x01, START_BYTE, ESCAPE_BYTE, END_BYTE, 0x05];
+        let configs = [
+            ProtocolConfig::default(),
+            ProtocolConfig {
+                checksum_kind: ChecksumKind::Crc16,
+                checksum_mode: ChecksumMode::EscapedPayload,
+                ..Default::default()
+            },
+            ProtocolConfig {
+                framing: Framing::EscapedLong,
+                ..Default::default()
+            },
+            ProtocolConfig {
+                framing: Framing::LengthPrefixed,
+                checksum_kind: ChecksumKind::Crc16,
+                ..Default::default()
+            },
+        ];
+        for config in configs {
+            let frame = encode_frame::<32>(&payload, &config).unwrap();
+            assert_eq!(
+                frame.as_slice(),
+                Packet::new_with_config(payload.to_vec(), &config).to_bytes()
+            );
+            assert_eq!(
+                decode_frame::<8>(&frame, &config).unwrap().as_slice(),
+                payload
+            );
+        }
+    }
+
+    #[test]
+    fn test_frame_buffer_full() {
+        let config = ProtocolConfig::default();
+        let payload = [0x01, 0x02, 0x03, 0x04];
+        assert_eq!(
+            encode_frame::<7>(&payload, &config),
+            Err(SimpError::BufferFull)
+        );
+        let frame = encode_frame::<8>(&payload, &config).unwrap();
+        assert_eq!(
+            decode_frame::<3>(&frame, &config),
+            Err(SimpError::BufferFull)
+        );
+
+        let payload = [0x00; 300];
+        assert_eq!(
+            encode_frame::<512>(&payload, &config),
+            Err(SimpError::PayloadTooLarge)
+        );
+    }
+
+    #[test]
+    fn test_send_and_receive_frame() {
+        let config = ProtocolConfig::default();
+        let clock = MockClock::new();
+        let mut uart = MockUart::new();
+        send_frame::<16>(&mut uart, &[0x01, END_BYTE, 0x03], &config).unwrap();
+
+        uart.set_read_data(uart.get_written_data());
+        let payload =
+            receive_frame::<16>(&mut uart, Duration::from_secs(1), &config, &clock).unwrap();
+        assert_eq!(payload.as_slice(), [0x01, END_BYTE, 0x03]);
+
+        uart.set_read_data(Packet::new(vec![0x00; 20]).to_bytes());
+        assert_eq!(
+            receive_frame::<16>(&mut uart, Duration::from_secs(1), &config, &clock),
+            Err(SimpError::FrameTooLarge)
+        );
+    }
+}
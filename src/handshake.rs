@@ -0,0 +1,154 @@
+use crate::clock::{Clock, SystemClock};
+use crate::config::ProtocolConfig;
+use crate::error::SimpError;
+use crate::packet::Packet;
+use crate::receiver::PacketReceiver;
+use crate::uart::{send_packet, Uart};
+use std::time::Duration;
+
+/// Payload prefix of the frame that carries a config fingerprint
+pub const CONFIG_HANDSHAKE: [u8; 3] = *b"CFG";
+
+/// Function to check that the other end of the link uses a matching config
+///
+/// Sends the fingerprint of `config` and waits up to `timeout` for the other end, running
+/// `receive_config_handshake`, to answer with its own. Fails with
+/// `SimpError::ConfigMismatch` if the fingerprints differ, so a misconfigured link is
+/// caught before any data is sent.
+pub fn send_config_handshake(
+    uart: &mut impl Uart,
+    config: &ProtocolConfig,
+    timeout: Duration,
+) -> Result<(), SimpError> {
+    send_config_handshake_with_clock(uart, config, timeout, &SystemClock::new())
+}
+
+/// Function to check the config of the other end, timing the wait with the given clock
+pub fn send_config_handshake_with_clock(
+    uart: &mut impl Uart,
+    config: &ProtocolConfig,
+    timeout: Duration,
+    clock: &impl Clock,
+) -> Result<(), SimpError> {
+    send_packet(uart, &handshake_packet(config))?;
+
+    let mut receiver = PacketReceiver::new(ProtocolConfig::default());
+    let start_time = clock.now();
+    while clock.now() - start_time < timeout {
+        match receiver.receive(uart) {
+            Ok(reply) => return check_fingerprint(&reply, config),
+            Err(SimpError::Timeout) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Err(SimpError::Timeout)
+}
+
+/// Function to answer a config handshake started with `send_config_handshake`
+///
+/// Replies with the fingerprint of `config` whether or not it matches, so both ends learn
+/// about a mismatch, then fails with `SimpError::ConfigMismatch` if it does not.
+pub fn receive_config_handshake(
+    uart: &mut impl Uart,
+    config: &ProtocolConfig,
+) -> Result<(), SimpError> {
+    let request = PacketReceiver::new(ProtocolConfig::default()).receive(uart)?;
+    send_packet(uart, &handshake_packet(config))?;
+    check_fingerprint(&request, config)
+}
+
+/// Builds the handshake frame for the given config.
+///
+/// It always uses the default framing and checksum, so it can be read by an end whose
+/// config is different, which is the case the handshake exists to catch.
+fn handshake_packet(config: &ProtocolConfig) -> Packet {
+    let mut payload = CONFIG_HANDSHAKE.to_vec();
+    payload.extend(config.fingerprint().to_le_bytes());
+    Packet::new(payload)
+}
+
+fn check_fingerprint(packet: &Packet, config: &ProtocolConfig) -> Result<(), SimpError> {
+    let fingerprint = packet
+        .payload
+        .strip_prefix(&CONFIG_HANDSHAKE)
+        .filter(|fingerprint| fingerprint.len() == 2)
+        .ok_or(SimpError::InvalidHandshake)?;
+    if fingerprint != config.fingerprint().to_le_bytes() {
+        return Err(SimpError::ConfigMismatch);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::mocks::MockUart;
+    use crate::packet::ChecksumKind;
+
+    /// Runs a handshake between ends with the given configs and returns both outcomes.
+    fn handshake(
+        sender: &ProtocolConfig,
+        receiver: &ProtocolConfig,
+    ) -> (Result<(), SimpError>, Result<(), SimpError>) {
+        let mut receiver_uart = MockUart::new();
+        receiver_uart.set_read_data(handshake_packet(sender).to_bytes());
+        let received = receive_config_handshake(&mut receiver_uart, receiver);
+
+        let mut sender_uart = MockUart::new();
+        sender_uart.set_read_data(receiver_uart.get_written_data());
+        let sent = send_config_handshake(&mut sender_uart, sender, Duration::from_millis(50));
+        assert_eq!(
+            sender_uart.get_written_data(),
+            handshake_packet(sender).to_bytes()
+        );
+        (sent, received)
+    }
+
+    #[test]
+    fn test_matching_configs() {
+        let config = ProtocolConfig {
+            checksum_kind: ChecksumKind::Crc16,
+            ..Default::default()
+        };
+        assert_eq!(handshake(&config, &config), (Ok(()), Ok(())));
+
+        // Receive-side limits don't have to match
+        let limited = ProtocolConfig {
+            max_packets: Some(10),
+            ..config
+        };
+        assert_eq!(handshake(&config, &limited), (Ok(()), Ok(())));
+    }
+
+    #[test]
+    fn test_mismatched_configs() {
+        let sender = ProtocolConfig::default();
+        let receiver = ProtocolConfig {
+            checksum_kind: ChecksumKind::Crc16,
+            ..Default::default()
+        };
+        assert_eq!(
+            handshake(&sender, &receiver),
+            (
+                Err(SimpError::ConfigMismatch),
+                Err(SimpError::ConfigMismatch)
+            )
+        );
+    }
+
+    #[test]
+    fn test_handshake_timeout() {
+        let clock = MockClock::new();
+        let mut uart = MockUart::with_clock(&clock, Duration::from_millis(10));
+
+        let result = send_config_handshake_with_clock(
+            &mut uart,
+            &ProtocolConfig::default(),
+            Duration::from_millis(50),
+            &clock,
+        );
+        assert_eq!(result, Err(SimpError::Timeout));
+        assert_eq!(clock.now(), Duration::from_millis(50));
+    }
+}
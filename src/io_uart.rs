@@ -0,0 +1,123 @@
+use crate::error::SimpError;
+use crate::uart::Uart;
+use std::io::{ErrorKind, Read, Write};
+
+/// UART over any byte stream, such as a file, a socket, or an in-memory cursor
+///
+/// A read that returns no bytes means the stream has ended, so it is reported as
+/// `SimpError::ConnectionClosed` and the receive functions stop instead of waiting for
+/// data that will never come. A read that times out or would block is treated as no
+/// data being available yet. A write hands the whole buffer to the stream, retrying
+/// short writes, so a frame is never cut off partway through.
+pub struct IoUart<T: Read + Write> {
+    inner: T,
+}
+
+impl<T: Read + Write> IoUart<T> {
+    /// Creates a UART reading from and writing to the given stream.
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+
+    /// Consumes the UART and returns the stream.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: Read + Write> Uart for IoUart<T> {
+    fn write(&mut self, data: &[u8]) -> Result<usize, SimpError> {
+        self.inner
+            .write_all(data)
+            .map_err(|_| SimpError::Uart("Failed to write to stream"))?;
+        Ok(data.len())
+    }
+
+    fn read(&mut self) -> Option<u8> {
+        self.try_read().ok().flatten()
+    }
+
+    fn try_read(&mut self) -> Result<Option<u8>, SimpError> {
+        let mut buffer = [0u8; 1];
+        match self.inner.read(&mut buffer) {
+            Ok(0) => Err(SimpError::ConnectionClosed),
+            Ok(_) => Ok(Some(buffer[0])),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(None),
+            Err(e) if e.kind() == ErrorKind::Interrupted => Ok(None),
+            Err(_) => Err(SimpError::Uart("Failed to read from stream")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::Packet;
+    use crate::uart::{receive_packet, receive_packet_with_timeout, send_packet};
+    use std::io::Cursor;
+    use std::time::Duration;
+
+    /// Stream that accepts at most two bytes per write, like a full socket buffer
+    struct ShortWrites(Vec<u8>);
+
+    impl Read for ShortWrites {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Ok(0)
+        }
+    }
+
+    impl Write for ShortWrites {
+        fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+            let len = data.len().min(2);
+            self.0.extend_from_slice(&data[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_short_writes_send_whole_frame() {
+        let packet = Packet::new(vec![0x01, 0x02, 0x03, 0x04, 0x05]);
+        let mut uart = IoUart::new(ShortWrites(Vec::new()));
+
+        send_packet(&mut uart, &packet).unwrap();
+        assert_eq!(uart.into_inner().0, packet.to_bytes());
+    }
+
+    #[test]
+    fn test_eof_mid_frame() {
+        let bytes = Packet::new(vec![0x01, 0x02, 0x03]).to_bytes();
+        let mut uart = IoUart::new(Cursor::new(bytes[..4].to_vec()));
+
+        assert_eq!(
+            receive_packet(&mut uart).err(),
+            Some(SimpError::ConnectionClosed)
+        );
+        // A timeout does not keep it waiting either
+        let mut uart = IoUart::new(Cursor::new(bytes[..4].to_vec()));
+        assert_eq!(
+            receive_packet_with_timeout(&mut uart, Duration::from_secs(60)).err(),
+            Some(SimpError::ConnectionClosed)
+        );
+    }
+
+    #[test]
+    fn test_frames_from_stream() {
+        let mut bytes = Packet::new(vec![0x01, 0x02, 0x03]).to_bytes();
+        bytes.extend(Packet::new(vec![0x04]).to_bytes());
+        let mut uart = IoUart::new(Cursor::new(bytes));
+
+        assert_eq!(
+            receive_packet(&mut uart).unwrap().payload,
+            vec![0x01, 0x02, 0x03]
+        );
+        assert_eq!(receive_packet(&mut uart).unwrap().payload, vec![0x04]);
+        assert_eq!(
+            receive_packet(&mut uart).err(),
+            Some(SimpError::ConnectionClosed)
+        );
+    }
+}
@@ -1,2 +1,37 @@
+//! Without the default `std` feature the crate is `no_std` and needs no allocator. It then
+//! provides the `Uart` trait, the protocol config, and, with the `heapless` feature, the
+//! fixed-capacity framing in the `frame` module.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "async")]
+pub mod async_uart;
+pub mod buffer;
+pub mod clock;
+#[cfg(feature = "std")]
+pub mod compression;
+pub mod config;
+#[cfg(feature = "crossbeam")]
+pub mod crossbeam_uart;
+#[cfg(feature = "std")]
+pub mod diagnostics;
+#[cfg(feature = "std")]
+pub mod dry_run;
+pub mod error;
+#[cfg(feature = "heapless")]
+pub mod frame;
+#[cfg(feature = "std")]
+pub mod handshake;
+#[cfg(feature = "std")]
+pub mod io_uart;
+#[cfg(feature = "std")]
+pub mod metadata;
+#[cfg(test)]
+mod mocks;
 pub mod packet;
+pub mod rate_limited_uart;
+#[cfg(feature = "std")]
+pub mod receiver;
+pub mod retry;
+#[cfg(feature = "std")]
+pub mod tee_uart;
 pub mod uart;
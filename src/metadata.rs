@@ -0,0 +1,137 @@
+use crate::config::ProtocolConfig;
+use crate::error::SimpError;
+use crate::uart::{receive_multiple_packets_with_config, send_multiple_packets_with_config, Uart};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Key/value metadata sent ahead of the data of a multi-packet transfer
+pub type Metadata = HashMap<String, Vec<u8>>;
+
+/// Encodes metadata as length-prefixed key/value pairs
+///
+/// The block starts with its own length as a little-endian `u16`, followed by each pair
+/// as a one-byte key length, the UTF-8 key, a little-endian `u16` value length and the
+/// value.
+pub fn encode_metadata(metadata: &Metadata) -> Result<Vec<u8>, SimpError> {
+    let mut block = Vec::new();
+    for (key, value) in metadata {
+        let key_len = u8::try_from(key.len()).map_err(|_| SimpError::MetadataTooLarge)?;
+        let value_len = u16::try_from(value.len()).map_err(|_| SimpError::MetadataTooLarge)?;
+        block.push(key_len);
+        block.extend_from_slice(key.as_bytes());
+        block.extend_from_slice(&value_len.to_le_bytes());
+        block.extend_from_slice(value);
+    }
+
+    let block_len = u16::try_from(block.len()).map_err(|_| SimpError::MetadataTooLarge)?;
+    let mut encoded = block_len.to_le_bytes().to_vec();
+    encoded.extend(block);
+    Ok(encoded)
+}
+
+/// Decodes a metadata block from the start of `bytes`
+///
+/// Returns the metadata and the bytes that follow the block.
+pub fn decode_metadata(bytes: &[u8]) -> Result<(Metadata, &[u8]), SimpError> {
+    let (block, rest) = take_prefixed(bytes)?;
+
+    let mut metadata = Metadata::new();
+    let mut remaining = block;
+    while let Some((&key_len, after_len)) = remaining.split_first() {
+        let key_len = key_len as usize;
+        if after_len.len() < key_len {
+            return Err(SimpError::InvalidMetadata);
+        }
+        let (key, after_key) = after_len.split_at(key_len);
+        let key = std::str::from_utf8(key).map_err(|_| SimpError::InvalidMetadata)?;
+        let (value, after_value) = take_prefixed(after_key)?;
+        metadata.insert(key.to_string(), value.to_vec());
+        remaining = after_value;
+    }
+    Ok((metadata, rest))
+}
+
+/// Splits off a slice prefixed with its little-endian `u16` length
+fn take_prefixed(bytes: &[u8]) -> Result<(&[u8], &[u8]), SimpError> {
+    if bytes.len() < 2 {
+        return Err(SimpError::InvalidMetadata);
+    }
+    let (len, rest) = bytes.split_at(2);
+    let len = u16::from_le_bytes([len[0], len[1]]) as usize;
+    if rest.len() < len {
+        return Err(SimpError::InvalidMetadata);
+    }
+    Ok(rest.split_at(len))
+}
+
+/// Function to send multiple packets preceded by a metadata block
+///
+/// The metadata travels in the first packet(s) of the transfer, ahead of the data, so
+/// both ends must agree to use it.
+pub fn send_multiple_packets_with_metadata(
+    uart: &mut impl Uart,
+    metadata: &Metadata,
+    data: &[u8],
+    retries: usize,
+    timeout: Duration,
+    config: &ProtocolConfig,
+) -> Result<(), SimpError> {
+    let mut transfer = encode_metadata(metadata)?;
+    transfer.extend_from_slice(data);
+    send_multiple_packets_with_config(uart, &transfer, retries, timeout, config)
+}
+
+/// Function to receive multiple packets sent with `send_multiple_packets_with_metadata`
+pub fn receive_multiple_packets_with_metadata(
+    uart: &mut impl Uart,
+    config: &ProtocolConfig,
+) -> Result<(Metadata, Vec<u8>), SimpError> {
+    let transfer = receive_multiple_packets_with_config(uart, config)?;
+    let (metadata, data) = decode_metadata(&transfer)?;
+    Ok((metadata, data.to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mocks::MockUart;
+    use crate::uart::ACK_BYTE;
+
+    #[test]
+    fn test_metadata_roundtrip() {
+        let mut metadata = Metadata::new();
+        metadata.insert("filename".to_string(), b"firmware.bin".to_vec());
+        metadata.insert("size".to_string(), 600u32.to_le_bytes().to_vec());
+        let data = vec![0x01; 600];
+        let config = ProtocolConfig::default();
+
+        let mut sender = MockUart::new();
+        sender.set_read_data(vec![ACK_BYTE; 3]);
+        send_multiple_packets_with_metadata(
+            &mut sender,
+            &metadata,
+            &data,
+            3,
+            Duration::from_millis(100),
+            &config,
+        )
+        .unwrap();
+
+        let mut receiver = MockUart::new();
+        receiver.set_read_data(sender.get_written_data());
+        let (received_metadata, received_data) =
+            receive_multiple_packets_with_metadata(&mut receiver, &config).unwrap();
+        assert_eq!(received_metadata, metadata);
+        assert_eq!(received_data, data);
+    }
+
+    #[test]
+    fn test_decode_truncated_metadata() {
+        let mut metadata = Metadata::new();
+        metadata.insert("filename".to_string(), b"firmware.bin".to_vec());
+        let encoded = encode_metadata(&metadata).unwrap();
+
+        assert!(decode_metadata(&encoded[..encoded.len() - 1]).is_err());
+        assert!(decode_metadata(&[0x01]).is_err());
+    }
+}
@@ -0,0 +1,244 @@
+use crate::clock::{Clock, MockClock};
+use crate::config::ProtocolConfig;
+use crate::error::SimpError;
+use crate::packet::Packet;
+use crate::uart::{receive_packet_with_config, send_packet, Uart};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// In-memory UART used by the unit tests
+pub struct MockUart<'a> {
+    // This will hold the data that the mock UART "sends" or "receives"
+    write_data: RefCell<Vec<u8>>,
+    read_data: Arc<Mutex<VecDeque<u8>>>,
+    // Read data of the other endpoint of a pair, which receives everything written
+    peer_read_data: Option<Arc<Mutex<VecDeque<u8>>>>,
+    // When set, writes succeed without writing anything, like a stuck device
+    write_stalled: bool,
+    // When set, reads fail with this error once the read data runs out
+    read_error: Option<&'static str>,
+    // Number of upcoming writes that fail
+    failed_writes: usize,
+    // Number of upcoming bytes that are lost on the line
+    lost_bytes: usize,
+    // Position in the read data of a byte to flip on the way in
+    corrupt_index: Option<usize>,
+    // Number of bytes taken from the read data so far
+    bytes_read: Cell<usize>,
+    // When set, every read advances this clock by the tick
+    clock: Option<&'a MockClock>,
+    tick: Duration,
+    // Clock delay before the read data arrives, and between its bytes
+    latency: Duration,
+    byte_time: Duration,
+    // Clock time at which the next byte of read data arrives
+    next_arrival: Cell<Duration>,
+}
+
+impl MockUart<'static> {
+    pub fn new() -> Self {
+        MockUart {
+            write_data: RefCell::new(Vec::new()),
+            read_data: Arc::new(Mutex::new(VecDeque::new())),
+            peer_read_data: None,
+            write_stalled: false,
+            read_error: None,
+            failed_writes: 0,
+            lost_bytes: 0,
+            corrupt_index: None,
+            bytes_read: Cell::new(0),
+            clock: None,
+            tick: Duration::ZERO,
+            latency: Duration::ZERO,
+            byte_time: Duration::ZERO,
+            next_arrival: Cell::new(Duration::ZERO),
+        }
+    }
+
+    /// Creates two linked endpoints, where bytes written to one are read from the other.
+    pub fn pair() -> (Self, Self) {
+        let mut first = MockUart::new();
+        let mut second = MockUart::new();
+        first.peer_read_data = Some(second.read_data.clone());
+        second.peer_read_data = Some(first.read_data.clone());
+        (first, second)
+    }
+}
+
+impl<'a> MockUart<'a> {
+    /// Creates a mock whose reads each advance `clock` by `tick`, like a read that waits.
+    pub fn with_clock(clock: &'a MockClock, tick: Duration) -> Self {
+        MockUart {
+            clock: Some(clock),
+            tick,
+            ..MockUart::new()
+        }
+    }
+
+    /// Delays the read data on the mock's clock, like a peer answering over a slow line.
+    ///
+    /// The data arrives `latency` after it is set or after the last write, then one byte
+    /// every `byte_time`. Reads before that find nothing.
+    pub fn delay_reads(&mut self, latency: Duration, byte_time: Duration) -> &mut Self {
+        self.latency = latency;
+        self.byte_time = byte_time;
+        self.schedule_arrival(latency);
+        self
+    }
+
+    fn schedule_arrival(&self, delay: Duration) {
+        if let Some(clock) = self.clock {
+            self.next_arrival.set(clock.now() + delay);
+        }
+    }
+
+    pub fn set_read_data(&self, data: Vec<u8>) {
+        *self.read_data.lock().unwrap() = data.into();
+        self.bytes_read.set(0);
+        self.schedule_arrival(self.latency);
+    }
+
+    pub fn stall_writes(&mut self) {
+        self.write_stalled = true;
+    }
+
+    pub fn fail_reads(&mut self, error: &'static str) {
+        self.read_error = Some(error);
+    }
+
+    /// Makes the next `count` writes fail without writing anything.
+    pub fn fail_next_write(&mut self, count: usize) -> &mut Self {
+        self.failed_writes = count;
+        self
+    }
+
+    /// Loses the next `count` bytes of read data on the line, as if they were never sent.
+    pub fn lose_next_bytes(&mut self, count: usize) -> &mut Self {
+        self.lost_bytes = count;
+        self
+    }
+
+    /// Flips every bit of the byte at `index` in the read data as it is read, once.
+    pub fn corrupt_byte_at(&mut self, index: usize) -> &mut Self {
+        self.corrupt_index = Some(index);
+        self
+    }
+
+    pub fn get_written_data(&self) -> Vec<u8> {
+        self.write_data.borrow().clone()
+    }
+}
+
+impl Uart for MockUart<'_> {
+    fn write(&mut self, data: &[u8]) -> Result<usize, SimpError> {
+        if self.failed_writes > 0 {
+            self.failed_writes -= 1;
+            return Err(SimpError::Uart("Failed to write to serial port"));
+        }
+        if self.write_stalled {
+            return Ok(0);
+        }
+        self.write_data.borrow_mut().extend_from_slice(data);
+        if let Some(peer_read_data) = &self.peer_read_data {
+            peer_read_data.lock().unwrap().extend(data);
+        }
+        self.schedule_arrival(self.latency);
+        Ok(data.len())
+    }
+
+    fn read(&mut self) -> Option<u8> {
+        if let Some(clock) = self.clock {
+            clock.advance(self.tick);
+            if clock.now() < self.next_arrival.get() {
+                return None;
+            }
+        }
+        let mut read_data = self.read_data.lock().unwrap();
+        loop {
+            let byte = read_data.pop_front()?;
+            let index = self.bytes_read.replace(self.bytes_read.get() + 1);
+            if self.lost_bytes > 0 {
+                self.lost_bytes -= 1;
+                continue;
+            }
+            self.schedule_arrival(self.byte_time);
+            if self.corrupt_index == Some(index) {
+                self.corrupt_index = None;
+                return Some(!byte);
+            }
+            return Some(byte);
+        }
+    }
+
+    fn try_read(&mut self) -> Result<Option<u8>, SimpError> {
+        match (self.read(), self.read_error) {
+            (None, Some(error)) => Err(SimpError::Uart(error)),
+            (byte, _) => Ok(byte),
+        }
+    }
+}
+
+// Async reads wait forever once the read data runs out, like an idle line
+#[cfg(feature = "async")]
+impl crate::async_uart::AsyncUart for MockUart<'_> {
+    async fn write(&mut self, data: &[u8]) -> Result<usize, SimpError> {
+        Uart::write(self, data)
+    }
+
+    async fn read(&mut self) -> Result<u8, SimpError> {
+        match self.try_read()? {
+            Some(byte) => Ok(byte),
+            None => std::future::pending().await,
+        }
+    }
+}
+
+/// Encodes data as the frames of a multi-packet transfer, one per packet
+///
+/// The data is chunked and numbered the way the multi-packet send does it for the given
+/// config, and a transfer that fills its last packet ends with a sequence-only packet.
+pub fn multi_packet_frames(data: &[u8], config: &ProtocolConfig) -> Vec<Vec<u8>> {
+    let chunk_size = config.max_payload_size - config.sequence_width.bytes();
+    let mut chunks: Vec<&[u8]> = data.chunks(chunk_size).collect();
+    if data.len().is_multiple_of(chunk_size) {
+        chunks.push(&[]);
+    }
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(index, chunk)| {
+            let mut payload = config.sequence_width.encode(index);
+            payload.extend_from_slice(chunk);
+            Packet::new_with_config(payload, config).to_bytes()
+        })
+        .collect()
+}
+
+/// Encodes data as the byte stream of a multi-packet transfer, see `multi_packet_frames`
+pub fn multi_packet_stream(data: &[u8], config: &ProtocolConfig) -> Vec<u8> {
+    multi_packet_frames(data, config).concat()
+}
+
+/// Sends a packet with the given payload through a MockUart and reads it back
+///
+/// Returns the decoded payload, so any disagreement between the send and receive paths
+/// shows up as a mismatch or an error.
+pub fn roundtrip(payload: &[u8]) -> Result<Vec<u8>, SimpError> {
+    roundtrip_with_config(payload, &ProtocolConfig::default())
+}
+
+/// Sends a packet through a MockUart using the given protocol config and reads it back
+pub fn roundtrip_with_config(
+    payload: &[u8],
+    config: &ProtocolConfig,
+) -> Result<Vec<u8>, SimpError> {
+    let mut uart = MockUart::new();
+    send_packet(
+        &mut uart,
+        &Packet::new_with_config(payload.to_vec(), config),
+    )?;
+    uart.set_read_data(uart.get_written_data());
+    Ok(receive_packet_with_config(&mut uart, config)?.payload)
+}
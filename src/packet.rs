@@ -1,57 +1,380 @@
+#[cfg(any(feature = "std", feature = "heapless"))]
+use crate::{config::ProtocolConfig, error::SimpError};
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+
 pub const START_BYTE: u8 = 0x7E;
 pub const END_BYTE: u8 = 0x7F;
 pub const ESCAPE_BYTE: u8 = 0x7D;
 pub const ESCAPE_XOR: u8 = 0x20;
 
+/// Selects which bytes the checksum is computed over
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumMode {
+    /// Checksum over the logical payload, before escaping
+    #[default]
+    Payload,
+    /// Checksum over the escaped payload exactly as it appears on the wire
+    ///
+    /// Many embedded peers checksum the bytes they transmit rather than the data they
+    /// were asked to send. Use this mode to interoperate with them.
+    ///
+    /// The covered range is the escaped payload only: every byte after the length field
+    /// up to the first checksum byte. The start byte, the length field, the checksum and
+    /// the end byte are not covered, so a peer that also checksums its header is not
+    /// compatible with this mode. For `7E 04 01 7D 5E 02 DE 7F` the Sum8 checksum `DE`
+    /// covers `01 7D 5E 02`.
+    EscapedPayload,
+}
+
+/// Selects the algorithm used to compute the packet checksum
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChecksumKind {
+    /// Wrapping 8-bit sum of the bytes, one checksum byte
+    #[default]
+    Sum8,
+    /// CRC-16/CCITT-FALSE, two checksum bytes sent most significant first
+    ///
+    /// Catches transposed bytes and most multi-bit errors that the sum misses.
+    Crc16,
+}
+
+impl ChecksumKind {
+    /// Returns the number of bytes the checksum occupies in a frame.
+    pub fn width(self) -> usize {
+        match self {
+            ChecksumKind::Sum8 => 1,
+            ChecksumKind::Crc16 => 2,
+        }
+    }
+}
+
+/// Selects how frames are delimited on the wire
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Framing {
+    /// Start byte, one length byte, escaped payload, escaped checksum and end byte
+    ///
+    /// Control bytes in the payload and checksum are escaped, so the end byte only ever
+    /// closes a frame and a receiver that joins mid-stream resynchronises at the next one.
+    #[default]
+    Escaped,
+    /// Like `Escaped`, but with two length bytes sent least significant first
+    ///
+    /// Carries escaped payloads of up to 65535 bytes, such as firmware images, at the cost
+    /// of one more byte per frame.
+    EscapedLong,
+    /// Start byte, two length bytes sent least significant first, payload and checksum
+    ///
+    /// The payload is sent as is and the length alone delimits the frame, which saves the
+    /// escaping overhead on binary data. There is no end byte to resynchronise on: a
+    /// receiver that joins mid-stream or loses a byte can take a payload byte for the
+    /// start byte and misread the frames after it until the checksum catches the error.
+    LengthPrefixed,
+}
+
+impl Framing {
+    /// Returns the number of bytes the length field occupies.
+    pub fn length_width(self) -> usize {
+        match self {
+            Framing::Escaped => 1,
+            Framing::EscapedLong | Framing::LengthPrefixed => 2,
+        }
+    }
+
+    /// Returns whether the payload is escaped and the frame closed with the end byte.
+    pub fn is_escaped(self) -> bool {
+        self != Framing::LengthPrefixed
+    }
+
+    /// Returns the number of bytes a frame adds around its payload.
+    ///
+    /// With an escaped framing this is the minimum: each escaped checksum byte adds one more.
+    pub fn overhead(self, checksum_kind: ChecksumKind) -> usize {
+        1 + self.length_width() + checksum_kind.width() + self.is_escaped() as usize
+    }
+
+    /// Returns the largest payload, after any escaping, the length field can describe.
+    pub fn max_payload(self) -> usize {
+        (1 << (8 * self.length_width())) - 1
+    }
+}
+
+/// Error describing a frame whose length byte disagrees with its escaped payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthMismatch {
+    /// Escaped payload length according to the length byte
+    pub declared: usize,
+    /// Escaped payload length actually found between the length and checksum bytes
+    pub observed: usize,
+}
+
+/// One field that differs between two packets, as reported by `Packet::diff`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldDiff {
+    StartByte {
+        expected: u8,
+        actual: u8,
+    },
+    Length {
+        expected: u16,
+        actual: u16,
+    },
+    /// A payload byte, or None where one payload is shorter than the other
+    Payload {
+        index: usize,
+        expected: Option<u8>,
+        actual: Option<u8>,
+    },
+    Checksum {
+        expected: u16,
+        actual: u16,
+    },
+    EndByte {
+        expected: u8,
+        actual: u8,
+    },
+}
+
 /// Represents a packet with start, length, payload, checksum, and end bytes
+///
+/// Only available with the `std` feature. Without it, the `frame` module encodes and
+/// decodes frames into fixed-capacity buffers instead.
+///
+/// With the `serde` feature a packet serializes to its logical payload, checksum
+/// algorithm, and framing. The start and end bytes are the defaults on deserialize, and
+/// the length and checksum are recomputed, so a deserialized packet is always well-formed.
+#[cfg(feature = "std")]
 pub struct Packet {
     /// Start byte (START_BYTE)
     pub start_byte: u8,
     /// Length of payload
-    pub length: u8,
+    pub length: u16,
     /// Payload
     pub payload: Vec<u8>,
-    /// Checksum
-    pub checksum: u8,
-    /// End byte (END_BYTE)
+    /// Checksum, of which only the low byte is used with `ChecksumKind::Sum8`
+    pub checksum: u16,
+    /// Algorithm the checksum was computed with
+    pub checksum_kind: ChecksumKind,
+    /// How the packet is delimited on the wire
+    pub framing: Framing,
+    /// End byte (END_BYTE), not sent with `Framing::LengthPrefixed`
     pub end_byte: u8,
 }
 
+#[cfg(feature = "std")]
 impl Packet {
     /// Creates a new packet with the given payload.
-    /// 
+    ///
     /// The payload will be escaped and the checksum will be calculated.
     pub fn new(payload: Vec<u8>) -> Self {
-        let escaped_payload = Self::escape_payload(&payload);
-        let length = escaped_payload.len() as u8;
-        let checksum = Self::calculate_checksum(&escaped_payload);
+        Self::new_with_config(payload, &ProtocolConfig::default())
+    }
+
+    /// Creates a new packet with the given payload, checking that it fits the length field.
+    ///
+    /// Fails with `SimpError::PayloadTooLarge` if the payload, after escaping, is longer
+    /// than the length field can describe.
+    pub fn try_new(payload: Vec<u8>) -> Result<Self, SimpError> {
+        Self::try_new_with_config(payload, &ProtocolConfig::default())
+    }
+
+    /// Creates a new packet with the given payload and checksum algorithm.
+    pub fn new_with_checksum(payload: Vec<u8>, checksum_kind: ChecksumKind) -> Self {
+        let config = ProtocolConfig {
+            checksum_kind,
+            ..Default::default()
+        };
+        Self::new_with_config(payload, &config)
+    }
+
+    /// Creates a new packet with a two byte length field, for escaped payloads over 255 bytes.
+    pub fn new_long(payload: Vec<u8>) -> Self {
+        let config = ProtocolConfig {
+            framing: Framing::EscapedLong,
+            ..Default::default()
+        };
+        Self::new_with_config(payload, &config)
+    }
+
+    /// Creates a new packet with the given payload using the given protocol config.
+    ///
+    /// A payload that does not fit the length field after escaping is accepted, but its
+    /// frame can't be sent. Use `try_new_with_config` to catch that here.
+    pub fn new_with_config(payload: Vec<u8>, config: &ProtocolConfig) -> Self {
+        if !config.framing.is_escaped() {
+            return Self::from_escaped_with_config(payload, config);
+        }
+        let (escaped_payload, sum) = Self::escape_and_checksum(&payload, config);
+        let checksum = match config.checksum_kind {
+            // The single pass already computed it
+            ChecksumKind::Sum8 => sum as u16,
+            ChecksumKind::Crc16 => Self::checksum_of(&payload, &escaped_payload, config),
+        };
         Packet {
-            start_byte: START_BYTE,
-            length,
+            start_byte: config.start_byte,
+            length: Self::length_of(&escaped_payload),
             payload: escaped_payload,
             checksum,
-            end_byte: END_BYTE,
+            checksum_kind: config.checksum_kind,
+            framing: config.framing,
+            end_byte: config.end_byte,
         }
     }
 
+    /// Creates a new packet using the given protocol config, checking that it fits the length field.
+    pub fn try_new_with_config(
+        payload: Vec<u8>,
+        config: &ProtocolConfig,
+    ) -> Result<Self, SimpError> {
+        let packet = Self::new_with_config(payload, config);
+        packet.check_fits()?;
+        Ok(packet)
+    }
+
+    /// Creates a new packet from a payload that is already escaped.
+    ///
+    /// The payload is used as is, which lets forwarding nodes re-frame a received payload
+    /// without escaping it twice. The caller must make sure it really is escaped: a raw
+    /// START_BYTE or END_BYTE in it will end up on the wire and break the frame.
+    pub fn from_escaped(escaped_payload: Vec<u8>) -> Self {
+        Self::from_escaped_with_config(escaped_payload, &ProtocolConfig::default())
+    }
+
+    /// Creates a new packet from a payload that is already escaped using the given protocol config.
+    ///
+    /// With `Framing::LengthPrefixed` nothing is escaped, so the payload is taken as raw data.
+    pub fn from_escaped_with_config(escaped_payload: Vec<u8>, config: &ProtocolConfig) -> Self {
+        let checksum = if config.framing.is_escaped() {
+            Self::checksum_of(
+                &Self::unescape_payload(&escaped_payload),
+                &escaped_payload,
+                config,
+            )
+        } else {
+            Self::checksum_of(&escaped_payload, &escaped_payload, config)
+        };
+        Packet {
+            start_byte: config.start_byte,
+            length: Self::length_of(&escaped_payload),
+            payload: escaped_payload,
+            checksum,
+            checksum_kind: config.checksum_kind,
+            framing: config.framing,
+            end_byte: config.end_byte,
+        }
+    }
+
+    /// Returns the length field for an escaped payload, saturating at the field's maximum.
+    fn length_of(escaped_payload: &[u8]) -> u16 {
+        u16::try_from(escaped_payload.len()).unwrap_or(u16::MAX)
+    }
+
+    /// Fails with `SimpError::PayloadTooLarge` if the escaped payload overflows the length field.
+    fn check_fits(&self) -> Result<(), SimpError> {
+        if self.payload.len() > self.framing.max_payload() {
+            return Err(SimpError::PayloadTooLarge);
+        }
+        Ok(())
+    }
+
     /// Calculates the checksum of the given payload.
     pub fn calculate_checksum(payload: &[u8]) -> u8 {
         payload.iter().fold(0u8, |acc, &x| acc.wrapping_add(x))
     }
 
+    /// Calculates the checksum of the given payload with the given algorithm.
+    pub fn calculate_checksum_with_kind(payload: &[u8], checksum_kind: ChecksumKind) -> u16 {
+        match checksum_kind {
+            ChecksumKind::Sum8 => Self::calculate_checksum(payload) as u16,
+            ChecksumKind::Crc16 => Self::calculate_crc16(payload),
+        }
+    }
+
+    /// Calculates the CRC-16/CCITT-FALSE of the given payload.
+    pub fn calculate_crc16(payload: &[u8]) -> u16 {
+        crc16(payload.iter().copied())
+    }
+
+    /// Calculates the CRC-32 of the given data, as printed by zip and `crc32` tools.
+    pub fn calculate_crc32(data: &[u8]) -> u32 {
+        Self::update_crc32(0, data)
+    }
+
+    /// Extends the CRC-32 of earlier data with the bytes that follow it.
+    ///
+    /// Starting from 0 and feeding the data in pieces gives the same result as
+    /// `calculate_crc32` over all of it, so a hash can be kept while data arrives.
+    pub fn update_crc32(crc: u32, data: &[u8]) -> u32 {
+        !data.iter().fold(!crc, |mut crc, &byte| {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 {
+                    (crc >> 1) ^ 0xEDB8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+            crc
+        })
+    }
+
+    /// Calculates the checksum the config calls for over the logical or escaped payload.
+    fn checksum_of(payload: &[u8], escaped_payload: &[u8], config: &ProtocolConfig) -> u16 {
+        let covered = match config.checksum_mode {
+            ChecksumMode::Payload => payload,
+            ChecksumMode::EscapedPayload => escaped_payload,
+        };
+        Self::calculate_checksum_with_kind(covered, config.checksum_kind)
+    }
+
     /// Escapes the given payload by replacing START_BYTE, END_BYTE, and ESCAPE_BYTE with their escaped versions.
     pub fn escape_payload(payload: &[u8]) -> Vec<u8> {
-        let mut escaped_payload = Vec::new();
-        for &byte in payload {
-            match byte {
-                START_BYTE | END_BYTE | ESCAPE_BYTE => {
-                    escaped_payload.push(ESCAPE_BYTE);
-                    escaped_payload.push(byte ^ ESCAPE_XOR);
-                }
-                _ => escaped_payload.push(byte),
+        Self::escape_payload_with_config(payload, &ProtocolConfig::default())
+    }
+
+    /// Escapes the given payload by replacing the configured start and end bytes, and ESCAPE_BYTE with their escaped versions.
+    pub fn escape_payload_with_config(payload: &[u8], config: &ProtocolConfig) -> Vec<u8> {
+        Self::escape_and_checksum(payload, config).0
+    }
+
+    /// Escapes the given payload and calculates its checksum.
+    ///
+    /// The payload is first summed and the bytes that need escaping counted, so the
+    /// escaped payload is allocated once at its final size and filled a run of plain bytes
+    /// at a time. A payload with nothing to escape, the common case for text and most
+    /// sensor data, is copied as is.
+    fn escape_and_checksum(payload: &[u8], config: &ProtocolConfig) -> (Vec<u8>, u8) {
+        let needs_escape =
+            |byte: u8| byte == config.start_byte || byte == config.end_byte || byte == ESCAPE_BYTE;
+        let mut checksum = Self::calculate_checksum(payload);
+        let escapes = payload.iter().filter(|&&byte| needs_escape(byte)).count();
+        if escapes == 0 {
+            return (payload.to_vec(), checksum);
+        }
+
+        let mut escaped_payload = Vec::with_capacity(payload.len() + escapes);
+        // Difference between the escaped and the logical checksum
+        let mut escape_adjustment = 0u8;
+        // Copy each run of plain bytes at once, escaping the byte that ends it
+        for run in payload.split_inclusive(|&byte| needs_escape(byte)) {
+            let (&last, plain) = run.split_last().expect("runs are never empty");
+            if !needs_escape(last) {
+                escaped_payload.extend_from_slice(run);
+                continue;
             }
+            escaped_payload.extend_from_slice(plain);
+            escaped_payload.extend_from_slice(&[ESCAPE_BYTE, last ^ ESCAPE_XOR]);
+            escape_adjustment = escape_adjustment
+                .wrapping_add(ESCAPE_BYTE)
+                .wrapping_add(last ^ ESCAPE_XOR)
+                .wrapping_sub(last);
         }
-        escaped_payload
+        if config.checksum_mode == ChecksumMode::EscapedPayload {
+            checksum = checksum.wrapping_add(escape_adjustment);
+        }
+        (escaped_payload, checksum)
     }
 
     /// Unescapes the given payload by replacing ESCAPE_BYTE with its unescaped version.
@@ -73,41 +396,404 @@ impl Packet {
     }
 
     /// Converts the packet to its byte representation.
+    ///
+    /// If the escaped payload is longer than the length field can describe, the field is
+    /// set to its maximum, so the receiver rejects the frame instead of misreading it. Use
+    /// `try_to_bytes` to catch that before sending.
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = vec![self.start_byte, self.length];
+        let mut bytes = vec![self.start_byte];
+        match self.framing.length_width() {
+            1 => bytes.push(u8::try_from(self.length).unwrap_or(u8::MAX)),
+            _ => bytes.extend(self.length.to_le_bytes()),
+        }
         bytes.extend(&self.payload);
-        bytes.push(self.checksum);
-        bytes.push(self.end_byte);
+        let checksum = self.checksum.to_be_bytes();
+        for &byte in &checksum[2 - self.checksum_kind.width()..] {
+            // The checksum is escaped like the payload, so it can't pass for the end byte
+            if self.framing.is_escaped()
+                && (byte == self.start_byte || byte == self.end_byte || byte == ESCAPE_BYTE)
+            {
+                bytes.extend([ESCAPE_BYTE, byte ^ ESCAPE_XOR]);
+            } else {
+                bytes.push(byte);
+            }
+        }
+        if self.framing.is_escaped() {
+            bytes.push(self.end_byte);
+        }
         bytes
     }
 
+    /// Converts the packet to its byte representation, checking that it fits the length field.
+    ///
+    /// Fails with `SimpError::PayloadTooLarge` if the escaped payload is longer than the
+    /// length field can describe.
+    pub fn try_to_bytes(&self) -> Result<Vec<u8>, SimpError> {
+        self.check_fits()?;
+        Ok(self.to_bytes())
+    }
+
     /// Creates a packet from its byte representation.
-    pub fn from_bytes(bytes: &[u8]) -> Result<Self, &'static str> {
-        if bytes.len() < 4 || bytes[0] != START_BYTE || bytes[bytes.len() - 1] != END_BYTE {
-            return Err("Invalid packet structure");
-        }
-        let length = bytes[1] as usize;
-        let checksum = bytes[bytes.len() - 2];
-        let payload = &bytes[2..bytes.len() - 2];
-        let unescaped_payload = Self::unescape_payload(payload);
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SimpError> {
+        Self::from_bytes_with_config(bytes, &ProtocolConfig::default())
+    }
 
-        if checksum != Self::calculate_checksum(&unescaped_payload) {
-            return Err("Checksum mismatch");
-        }
+    /// Creates a packet from its byte representation, validating it with the given checksum algorithm.
+    pub fn from_bytes_with_checksum(
+        bytes: &[u8],
+        checksum_kind: ChecksumKind,
+    ) -> Result<Self, SimpError> {
+        let config = ProtocolConfig {
+            checksum_kind,
+            ..Default::default()
+        };
+        Self::from_bytes_with_config(bytes, &config)
+    }
+
+    /// Creates a packet from the byte representation of a packet built with `new_long`.
+    pub fn from_bytes_long(bytes: &[u8]) -> Result<Self, SimpError> {
+        let config = ProtocolConfig {
+            framing: Framing::EscapedLong,
+            ..Default::default()
+        };
+        Self::from_bytes_with_config(bytes, &config)
+    }
+
+    /// Creates a packet from its byte representation using the given protocol config.
+    pub fn from_bytes_with_config(
+        bytes: &[u8],
+        config: &ProtocolConfig,
+    ) -> Result<Self, SimpError> {
+        // Reject a corrupt frame before allocating anything for it
+        verify_frame(bytes, config)?;
+        let (payload, checksum) = split_frame(bytes, config);
+        let unescaped_payload = if config.framing.is_escaped() {
+            Self::unescape_payload(payload)
+        } else {
+            payload.to_vec()
+        };
 
         Ok(Packet {
-            start_byte: START_BYTE,
-            length: length as u8,
+            start_byte: config.start_byte,
+            length: payload.len() as u16,
             payload: unescaped_payload,
             checksum,
-            end_byte: END_BYTE,
+            checksum_kind: config.checksum_kind,
+            framing: config.framing,
+            end_byte: config.end_byte,
         })
     }
+
+    /// Checks a frame's structure, length, and checksum without decoding it.
+    ///
+    /// The checksum is computed over the received bytes in place, unescaping them on the
+    /// fly, so nothing is allocated. On a noisy link, corrupt frames can be dropped this
+    /// way before any `Packet` is built for them.
+    pub fn verify_frame(bytes: &[u8], config: &ProtocolConfig) -> Result<(), SimpError> {
+        verify_frame(bytes, config)
+    }
+
+    /// Checks that the length byte of a frame matches the escaped payload it carries.
+    ///
+    /// The frame is expected to have at least the start, length, checksum, and end bytes.
+    /// On mismatch both lengths are reported, which helps track down escaping bugs.
+    pub fn check_length(bytes: &[u8]) -> Result<(), LengthMismatch> {
+        Self::check_length_with_config(bytes, &ProtocolConfig::default())
+    }
+
+    /// Checks the length field of a frame, allowing for the configured framing and checksum width.
+    pub fn check_length_with_config(
+        bytes: &[u8],
+        config: &ProtocolConfig,
+    ) -> Result<(), LengthMismatch> {
+        let declared = declared_length(bytes, config).unwrap_or(0);
+        let observed = if bytes.len() < config.framing.overhead(config.checksum_kind) {
+            0
+        } else {
+            split_frame(bytes, config).0.len()
+        };
+        if declared != observed {
+            return Err(LengthMismatch { declared, observed });
+        }
+        Ok(())
+    }
+
+    /// Returns the total length of the frame that starts `bytes`, once its length field is in.
+    ///
+    /// Lets a receiver read exactly one frame without scanning for the end byte, which is
+    /// the only way to find the end of a `Framing::LengthPrefixed` frame. With an escaped
+    /// framing the length is only known once the checksum bytes are in as well, since
+    /// either of them may be escaped.
+    pub fn frame_len(bytes: &[u8], config: &ProtocolConfig) -> Option<usize> {
+        frame_len(bytes, config)
+    }
+
+    /// Returns how many bytes escaping added to the payload on the wire.
+    ///
+    /// Each START_BYTE, end byte and ESCAPE_BYTE in the payload costs one extra byte, so a
+    /// payload made only of control bytes has 100% overhead.
+    pub fn escape_overhead(&self) -> usize {
+        self.length as usize - self.logical_payload().len()
+    }
+
+    /// Returns the payload before escaping, whether the packet was built or decoded.
+    ///
+    /// A packet built to be sent holds its payload escaped, with the length matching it,
+    /// while a decoded one holds it unescaped.
+    fn logical_payload(&self) -> Cow<'_, [u8]> {
+        if self.framing.is_escaped() && self.payload.len() == self.length as usize {
+            Cow::Owned(Self::unescape_payload(&self.payload))
+        } else {
+            Cow::Borrowed(&self.payload)
+        }
+    }
+
+    /// Lists the fields that differ between this packet and `other`, in frame order.
+    ///
+    /// `self` is treated as the expected packet and `other` as the actual one. Payload
+    /// bytes are compared one by one, so a single corrupted byte is reported at its index.
+    pub fn diff(&self, other: &Packet) -> Vec<FieldDiff> {
+        let mut diffs = Vec::new();
+        if self.start_byte != other.start_byte {
+            diffs.push(FieldDiff::StartByte {
+                expected: self.start_byte,
+                actual: other.start_byte,
+            });
+        }
+        if self.length != other.length {
+            diffs.push(FieldDiff::Length {
+                expected: self.length,
+                actual: other.length,
+            });
+        }
+        for index in 0..self.payload.len().max(other.payload.len()) {
+            let expected = self.payload.get(index).copied();
+            let actual = other.payload.get(index).copied();
+            if expected != actual {
+                diffs.push(FieldDiff::Payload {
+                    index,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        if self.checksum != other.checksum {
+            diffs.push(FieldDiff::Checksum {
+                expected: self.checksum,
+                actual: other.checksum,
+            });
+        }
+        if self.end_byte != other.end_byte {
+            diffs.push(FieldDiff::EndByte {
+                expected: self.end_byte,
+                actual: other.end_byte,
+            });
+        }
+        diffs
+    }
+
+    /// Reads the length byte and first payload byte of a frame without decoding it.
+    ///
+    /// Only the minimal prefix is inspected, so this is cheap enough to filter frames
+    /// before a full `from_bytes`. An escaped first payload byte is unescaped. The
+    /// checksum is not verified.
+    pub fn peek_header(bytes: &[u8]) -> Result<(u8, u8), SimpError> {
+        Self::peek_header_with_config(bytes, &ProtocolConfig::default())
+    }
+
+    /// Reads the length byte and first payload byte of a frame using the given protocol config.
+    ///
+    /// Only `Framing::Escaped` frames have a length byte; other framings are rejected.
+    pub fn peek_header_with_config(
+        bytes: &[u8],
+        config: &ProtocolConfig,
+    ) -> Result<(u8, u8), SimpError> {
+        if config.framing != Framing::Escaped || bytes.first() != Some(&config.start_byte) {
+            return Err(SimpError::InvalidStructure);
+        }
+        let length = *bytes.get(1).ok_or(SimpError::FrameTooShort)?;
+        if length == 0 {
+            return Err(SimpError::EmptyPacket);
+        }
+        let first = match *bytes.get(2).ok_or(SimpError::FrameTooShort)? {
+            ESCAPE_BYTE => bytes.get(3).ok_or(SimpError::FrameTooShort)? ^ ESCAPE_XOR,
+            byte => byte,
+        };
+        Ok((length, first))
+    }
+}
+
+/// Calculates the checksum of the bytes with the given algorithm as they are produced.
+#[cfg(any(feature = "std", feature = "heapless"))]
+pub(crate) fn checksum_of_iter(
+    bytes: impl Iterator<Item = u8>,
+    checksum_kind: ChecksumKind,
+) -> u16 {
+    match checksum_kind {
+        ChecksumKind::Sum8 => bytes.fold(0u8, |acc, x| acc.wrapping_add(x)) as u16,
+        ChecksumKind::Crc16 => crc16(bytes),
+    }
+}
+
+/// Calculates the CRC-16/CCITT-FALSE of the bytes as they are produced.
+pub(crate) fn crc16(bytes: impl Iterator<Item = u8>) -> u16 {
+    bytes.fold(0xFFFF, |mut crc: u16, byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// Yields the unescaped payload a byte at a time, without collecting it.
+#[cfg(any(feature = "std", feature = "heapless"))]
+pub(crate) fn unescaped(payload: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    payload
+        .iter()
+        .scan(false, |escape_next, &byte| {
+            Some(if core::mem::take(escape_next) {
+                Some(byte ^ ESCAPE_XOR)
+            } else if byte == ESCAPE_BYTE {
+                *escape_next = true;
+                None
+            } else {
+                Some(byte)
+            })
+        })
+        .flatten()
+}
+
+/// Checks a frame's structure, length, and checksum without decoding it, see
+/// `Packet::verify_frame`.
+#[cfg(any(feature = "std", feature = "heapless"))]
+pub(crate) fn verify_frame(bytes: &[u8], config: &ProtocolConfig) -> Result<(), SimpError> {
+    let escaped = config.framing.is_escaped();
+    let overhead = config.framing.overhead(config.checksum_kind);
+    if bytes.len() < overhead
+        || bytes[0] != config.start_byte
+        || (escaped && bytes[bytes.len() - 1] != config.end_byte)
+    {
+        return Err(SimpError::InvalidStructure);
+    }
+    let (payload, checksum) = split_frame(bytes, config);
+    let declared = declared_length(bytes, config).unwrap_or(0);
+    if declared != payload.len() {
+        return Err(SimpError::LengthMismatch(LengthMismatch {
+            declared,
+            observed: payload.len(),
+        }));
+    }
+
+    let expected = if escaped && config.checksum_mode == ChecksumMode::Payload {
+        checksum_of_iter(unescaped(payload), config.checksum_kind)
+    } else {
+        checksum_of_iter(payload.iter().copied(), config.checksum_kind)
+    };
+    if checksum != expected {
+        return Err(SimpError::ChecksumMismatch);
+    }
+    Ok(())
+}
+
+/// Splits a frame of at least the framing overhead into its payload, still escaped, and
+/// its unescaped checksum.
+#[cfg(any(feature = "std", feature = "heapless"))]
+pub(crate) fn split_frame<'a>(bytes: &'a [u8], config: &ProtocolConfig) -> (&'a [u8], u16) {
+    let escaped = config.framing.is_escaped();
+    let header_len = 1 + config.framing.length_width();
+    let mut checksum_start = bytes.len() - escaped as usize;
+    let mut checksum = 0u16;
+    // Walk back from the end, least significant byte first. An escaped checksum byte is
+    // the one preceded by ESCAPE_BYTE, which never appears in the stream on its own.
+    for shift in 0..config.checksum_kind.width() {
+        if checksum_start <= header_len {
+            break;
+        }
+        checksum_start -= 1;
+        let mut byte = bytes[checksum_start];
+        if escaped && checksum_start > header_len && bytes[checksum_start - 1] == ESCAPE_BYTE {
+            checksum_start -= 1;
+            byte ^= ESCAPE_XOR;
+        }
+        checksum |= (byte as u16) << (8 * shift);
+    }
+    (&bytes[header_len..checksum_start], checksum)
+}
+
+/// Returns the total length of the frame that starts `bytes`, see `Packet::frame_len`.
+#[cfg(any(feature = "std", feature = "heapless"))]
+pub(crate) fn frame_len(bytes: &[u8], config: &ProtocolConfig) -> Option<usize> {
+    let length = declared_length(bytes, config)?;
+    let mut len = 1 + config.framing.length_width() + length;
+    if !config.framing.is_escaped() {
+        return Some(len + config.checksum_kind.width());
+    }
+    for _ in 0..config.checksum_kind.width() {
+        len += if *bytes.get(len)? == ESCAPE_BYTE {
+            2
+        } else {
+            1
+        };
+    }
+    Some(len + 1)
+}
+
+/// Reads the payload length from the length field of a frame.
+#[cfg(any(feature = "std", feature = "heapless"))]
+fn declared_length(bytes: &[u8], config: &ProtocolConfig) -> Option<usize> {
+    let field = bytes.get(1..1 + config.framing.length_width())?;
+    Some(
+        field
+            .iter()
+            .rev()
+            .fold(0, |acc, &byte| (acc << 8) | byte as usize),
+    )
+}
+
+/// Serialized form of a `Packet`, leaving out everything that can be recomputed
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Packet")]
+struct SerializedPacket {
+    payload: Vec<u8>,
+    checksum_kind: ChecksumKind,
+    framing: Framing,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Packet {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedPacket {
+            payload: self.logical_payload().into_owned(),
+            checksum_kind: self.checksum_kind,
+            framing: self.framing,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Packet {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let packet = SerializedPacket::deserialize(deserializer)?;
+        let config = ProtocolConfig {
+            checksum_kind: packet.checksum_kind,
+            framing: packet.framing,
+            ..Default::default()
+        };
+        Ok(Packet::new_with_config(packet.payload, &config))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mocks::{roundtrip, roundtrip_with_config};
 
     #[test]
     fn test_packet_creation() {
@@ -116,8 +802,11 @@ mod tests {
 
         assert_eq!(packet.start_byte, START_BYTE);
         assert_eq!(packet.end_byte, END_BYTE);
-        assert_eq!(packet.length, packet.payload.len() as u8);
-        assert_eq!(packet.checksum, Packet::calculate_checksum(&packet.payload));
+        assert_eq!(packet.length, packet.payload.len() as u16);
+        assert_eq!(
+            packet.checksum,
+            Packet::calculate_checksum(&packet.payload) as u16
+        );
         assert_eq!(packet.payload, Packet::escape_payload(&payload));
     }
 
@@ -162,9 +851,9 @@ mod tests {
         let packet = Packet::new(payload.clone());
         let bytes = packet.to_bytes();
 
-        let mut expected = vec![START_BYTE, packet.length];
+        let mut expected = vec![START_BYTE, packet.length as u8];
         expected.extend_from_slice(&Packet::escape_payload(&payload));
-        expected.push(packet.checksum);
+        expected.push(packet.checksum as u8);
         expected.push(END_BYTE);
 
         assert_eq!(bytes, expected);
@@ -194,11 +883,11 @@ mod tests {
         let checksum_index = bytes.len() - 2;
     
         // Corrupt the checksum
-        bytes[checksum_index] = packet.checksum.wrapping_add(1);
+        bytes[checksum_index] = (packet.checksum as u8).wrapping_add(1);
     
         let result = Packet::from_bytes(&bytes);
         assert!(result.is_err());
-        assert_eq!(result.err().unwrap(), "Checksum mismatch");
+        assert_eq!(result.err().unwrap(), SimpError::ChecksumMismatch);
     }
 
     #[test]
@@ -206,6 +895,557 @@ mod tests {
         let invalid_bytes = vec![0x00, 0x01, 0x02]; // No START_BYTE, no END_BYTE
         let result = Packet::from_bytes(&invalid_bytes);
         assert!(result.is_err());
-        assert_eq!(result.err().unwrap(), "Invalid packet structure");
+        assert_eq!(result.err().unwrap(), SimpError::InvalidStructure);
+    }
+
+    #[test]
+    fn test_round_trip_with_control_bytes() {
+        let payload = vec![START_BYTE, 0x01, END_BYTE, ESCAPE_BYTE, 0x02];
+        for checksum_mode in [ChecksumMode::Payload, ChecksumMode::EscapedPayload] {
+            let config = ProtocolConfig {
+                checksum_mode,
+                ..Default::default()
+            };
+            assert_eq!(roundtrip_with_config(&payload, &config).unwrap(), payload);
+        }
+    }
+
+    #[test]
+    fn test_interop_with_escaped_payload_checksum() {
+        // Frame as produced by a peer that checksums the escaped bytes it transmits
+        let escaped_payload = vec![0x01, ESCAPE_BYTE, START_BYTE ^ ESCAPE_XOR, 0x02];
+        let mut bytes = vec![START_BYTE, escaped_payload.len() as u8];
+        bytes.extend_from_slice(&escaped_payload);
+        bytes.push(Packet::calculate_checksum(&escaped_payload));
+        bytes.push(END_BYTE);
+
+        let config = ProtocolConfig {
+            checksum_mode: ChecksumMode::EscapedPayload,
+            ..Default::default()
+        };
+        let parsed_packet =
+            Packet::from_bytes_with_config(&bytes, &config).expect("Failed to parse packet");
+        assert_eq!(parsed_packet.payload, vec![0x01, START_BYTE, 0x02]);
+
+        let result = Packet::from_bytes_with_config(&bytes, &ProtocolConfig::default());
+        assert_eq!(result.err().unwrap(), SimpError::ChecksumMismatch);
+    }
+
+    #[test]
+    fn test_escaped_payload_checksum_known_frames() {
+        // Captured peer frames carrying 01 7E 02; the checksum covers 01 7D 5E 02 only
+        let frames: [(ChecksumKind, &[u8]); 2] = [
+            (
+                ChecksumKind::Sum8,
+                &[0x7E, 0x04, 0x01, 0x7D, 0x5E, 0x02, 0xDE, 0x7F],
+            ),
+            (
+                ChecksumKind::Crc16,
+                &[0x7E, 0x04, 0x01, 0x7D, 0x5E, 0x02, 0x65, 0xDF, 0x7F],
+            ),
+        ];
+        for (checksum_kind, bytes) in frames {
+            let config = ProtocolConfig {
+                checksum_mode: ChecksumMode::EscapedPayload,
+                checksum_kind,
+                ..Default::default()
+            };
+            let parsed_packet =
+                Packet::from_bytes_with_config(bytes, &config).expect("Failed to parse packet");
+            assert_eq!(parsed_packet.payload, vec![0x01, START_BYTE, 0x02]);
+            assert_eq!(
+                Packet::new_with_config(vec![0x01, START_BYTE, 0x02], &config).to_bytes(),
+                bytes
+            );
+        }
+    }
+
+    #[test]
+    fn test_interop_with_payload_checksum() {
+        // Frame as produced by a peer that checksums the logical payload
+        let payload = vec![0x01, START_BYTE, 0x02];
+        let escaped_payload = Packet::escape_payload(&payload);
+        let mut bytes = vec![START_BYTE, escaped_payload.len() as u8];
+        bytes.extend_from_slice(&escaped_payload);
+        bytes.push(Packet::calculate_checksum(&payload));
+        bytes.push(END_BYTE);
+
+        let parsed_packet = Packet::from_bytes_with_config(&bytes, &ProtocolConfig::default())
+            .expect("Failed to parse packet");
+        assert_eq!(parsed_packet.payload, payload);
+
+        let config = ProtocolConfig {
+            checksum_mode: ChecksumMode::EscapedPayload,
+            ..Default::default()
+        };
+        let result = Packet::from_bytes_with_config(&bytes, &config);
+        assert_eq!(result.err().unwrap(), SimpError::ChecksumMismatch);
+    }
+
+    #[test]
+    fn test_from_escaped() {
+        let payload = vec![START_BYTE, 0x01, END_BYTE, ESCAPE_BYTE, 0x02];
+        let escaped_payload = Packet::escape_payload(&payload);
+        let packet = Packet::from_escaped(escaped_payload.clone());
+
+        // The control bytes are not escaped a second time
+        assert_eq!(packet.payload, escaped_payload);
+        assert_eq!(packet.length, escaped_payload.len() as u16);
+        assert_eq!(packet.to_bytes(), Packet::new(payload.clone()).to_bytes());
+
+        let parsed_packet = Packet::from_bytes(&packet.to_bytes()).expect("Failed to parse packet");
+        assert_eq!(parsed_packet.payload, payload);
+    }
+
+    #[test]
+    fn test_single_pass_matches_two_pass() {
+        let payloads = vec![
+            vec![],
+            vec![0x01, 0x02, 0x03],
+            vec![START_BYTE, 0x01, END_BYTE, ESCAPE_BYTE, 0x02],
+            (0..=255).collect::<Vec<u8>>(),
+        ];
+        for checksum_mode in [ChecksumMode::Payload, ChecksumMode::EscapedPayload] {
+            let config = ProtocolConfig {
+                checksum_mode,
+                ..Default::default()
+            };
+            for payload in &payloads {
+                let escaped_payload = Packet::escape_payload(payload);
+                let expected_checksum = match checksum_mode {
+                    ChecksumMode::Payload => Packet::calculate_checksum(payload),
+                    ChecksumMode::EscapedPayload => Packet::calculate_checksum(&escaped_payload),
+                };
+
+                let packet = Packet::new_with_config(payload.clone(), &config);
+                assert_eq!(packet.payload, escaped_payload);
+                assert_eq!(packet.checksum, expected_checksum as u16);
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_bytes_with_length_mismatch() {
+        let packet = Packet::new(vec![0x01, START_BYTE, 0x03]);
+        let mut bytes = packet.to_bytes();
+        assert_eq!(Packet::check_length(&bytes), Ok(()));
+
+        // Corrupt the length byte
+        bytes[1] = 7;
+
+        let mismatch = LengthMismatch {
+            declared: 7,
+            observed: 4,
+        };
+        let result = Packet::from_bytes(&bytes);
+        assert_eq!(result.err().unwrap(), SimpError::LengthMismatch(mismatch));
+        assert_eq!(Packet::check_length(&bytes), Err(mismatch));
+    }
+
+    #[test]
+    fn test_peek_header() {
+        let bytes = Packet::new(vec![0x42, 0x01, 0x02]).to_bytes();
+        assert_eq!(Packet::peek_header(&bytes), Ok((3, 0x42)));
+        // The prefix is enough
+        assert_eq!(Packet::peek_header(&bytes[..3]), Ok((3, 0x42)));
+
+        let escaped = Packet::new(vec![START_BYTE, 0x01]).to_bytes();
+        assert_eq!(Packet::peek_header(&escaped), Ok((3, START_BYTE)));
+    }
+
+    #[test]
+    fn test_peek_header_too_short() {
+        let bytes = Packet::new(vec![ESCAPE_BYTE]).to_bytes();
+        assert_eq!(
+            Packet::peek_header(&bytes[..1]),
+            Err(SimpError::FrameTooShort)
+        );
+        assert_eq!(
+            Packet::peek_header(&bytes[..2]),
+            Err(SimpError::FrameTooShort)
+        );
+        assert_eq!(
+            Packet::peek_header(&bytes[..3]),
+            Err(SimpError::FrameTooShort)
+        );
+        assert_eq!(Packet::peek_header(&[]), Err(SimpError::InvalidStructure));
+        assert_eq!(
+            Packet::peek_header(&Packet::new(Vec::new()).to_bytes()),
+            Err(SimpError::EmptyPacket)
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        assert_eq!(
+            roundtrip(&[0x01, 0x02, 0x03]).unwrap(),
+            vec![0x01, 0x02, 0x03]
+        );
+        assert_eq!(roundtrip(&[]).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_roundtrip_control_bytes() {
+        for payload in [
+            vec![START_BYTE],
+            vec![END_BYTE],
+            vec![ESCAPE_BYTE],
+            vec![START_BYTE, END_BYTE, ESCAPE_BYTE],
+            vec![ESCAPE_BYTE, ESCAPE_BYTE ^ ESCAPE_XOR, 0x01],
+        ] {
+            assert_eq!(roundtrip(&payload).unwrap(), payload);
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_every_checksum_value() {
+        // Single bytes cover every Sum8 checksum, two-byte payloads every CRC-16
+        for byte in 0..=u8::MAX {
+            assert_eq!(roundtrip(&[byte]).unwrap(), vec![byte]);
+        }
+
+        let config = ProtocolConfig {
+            checksum_kind: ChecksumKind::Crc16,
+            ..Default::default()
+        };
+        let mut covered = vec![false; 1 << 16];
+        for value in 0..=u16::MAX {
+            let payload = value.to_be_bytes();
+            covered[Packet::calculate_crc16(&payload) as usize] = true;
+            assert_eq!(roundtrip_with_config(&payload, &config).unwrap(), payload);
+        }
+        assert!(covered.iter().all(|&covered| covered));
+    }
+
+    #[test]
+    fn test_diff() {
+        let expected = Packet::new(vec![0x01, 0x02, 0x03]);
+        let actual = Packet::new(vec![0x01, 0x12, 0x03]);
+
+        assert_eq!(
+            expected.diff(&actual),
+            vec![
+                FieldDiff::Payload {
+                    index: 1,
+                    expected: Some(0x02),
+                    actual: Some(0x12),
+                },
+                FieldDiff::Checksum {
+                    expected: 0x06,
+                    actual: 0x16,
+                },
+            ]
+        );
+        assert!(expected
+            .diff(&Packet::new(vec![0x01, 0x02, 0x03]))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_diff_different_lengths() {
+        let expected = Packet::new(vec![0x01, 0x02]);
+        let actual = Packet::new(vec![0x01, 0x02, 0x00]);
+
+        assert_eq!(
+            expected.diff(&actual),
+            vec![
+                FieldDiff::Length {
+                    expected: 2,
+                    actual: 3,
+                },
+                FieldDiff::Payload {
+                    index: 2,
+                    expected: None,
+                    actual: Some(0x00),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_escape_overhead() {
+        let payload = [START_BYTE, END_BYTE, ESCAPE_BYTE].repeat(10);
+        let packet = Packet::new(payload.clone());
+        assert_eq!(packet.escape_overhead(), payload.len());
+
+        let decoded = Packet::from_bytes(&packet.to_bytes()).unwrap();
+        assert_eq!(decoded.escape_overhead(), payload.len());
+
+        assert_eq!(Packet::new(vec![0x01, 0x02]).escape_overhead(), 0);
+    }
+
+    #[test]
+    fn test_crc16_check_value() {
+        assert_eq!(Packet::calculate_crc16(b"123456789"), 0x29B1);
+    }
+
+    #[test]
+    fn test_crc32_check_value() {
+        assert_eq!(Packet::calculate_crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(Packet::calculate_crc32(b""), 0);
+        let crc = Packet::update_crc32(Packet::calculate_crc32(b"1234"), b"56789");
+        assert_eq!(crc, 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_crc16_roundtrip() {
+        let packet = Packet::new_with_checksum(vec![0x01, START_BYTE, 0x03], ChecksumKind::Crc16);
+        let bytes = packet.to_bytes();
+        // Two checksum bytes before the end byte
+        assert_eq!(bytes.len(), 2 + 4 + 2 + 1);
+        assert_eq!(
+            bytes[bytes.len() - 3..bytes.len() - 1],
+            packet.checksum.to_be_bytes()
+        );
+
+        let parsed = Packet::from_bytes_with_checksum(&bytes, ChecksumKind::Crc16).unwrap();
+        assert_eq!(parsed.payload, vec![0x01, START_BYTE, 0x03]);
+        assert_eq!(parsed.checksum, packet.checksum);
+        assert!(Packet::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_crc16_catches_errors_sum8_misses() {
+        // Swapped bytes and a pair of compensating changes keep the sum the same
+        let corruptions: [fn(&mut Vec<u8>); 2] = [
+            |bytes| bytes.swap(2, 3),
+            |bytes| {
+                bytes[2] = bytes[2].wrapping_add(1);
+                bytes[3] = bytes[3].wrapping_sub(1);
+            },
+        ];
+
+        for corrupt in corruptions {
+            let mut sum8 = Packet::new(vec![0x01, 0x02, 0x03]).to_bytes();
+            corrupt(&mut sum8);
+            assert!(Packet::from_bytes(&sum8).is_ok());
+
+            let mut crc16 =
+                Packet::new_with_checksum(vec![0x01, 0x02, 0x03], ChecksumKind::Crc16).to_bytes();
+            corrupt(&mut crc16);
+            assert_eq!(
+                Packet::from_bytes_with_checksum(&crc16, ChecksumKind::Crc16).err(),
+                Some(SimpError::ChecksumMismatch)
+            );
+        }
+
+        // Both catch a single corrupted byte
+        let mut crc16 =
+            Packet::new_with_checksum(vec![0x01, 0x02, 0x03], ChecksumKind::Crc16).to_bytes();
+        crc16[3] ^= 0x10;
+        assert!(Packet::from_bytes_with_checksum(&crc16, ChecksumKind::Crc16).is_err());
+    }
+
+    #[test]
+    fn test_verify_frame() {
+        let payload = vec![0x01, START_BYTE, ESCAPE_BYTE, END_BYTE];
+        for checksum_kind in [ChecksumKind::Sum8, ChecksumKind::Crc16] {
+            for checksum_mode in [ChecksumMode::Payload, ChecksumMode::EscapedPayload] {
+                let config = ProtocolConfig {
+                    checksum_kind,
+                    checksum_mode,
+                    ..Default::default()
+                };
+                let bytes = Packet::new_with_config(payload.clone(), &config).to_bytes();
+                assert_eq!(Packet::verify_frame(&bytes, &config), Ok(()));
+                assert_eq!(
+                    Packet::from_bytes_with_config(&bytes, &config)
+                        .unwrap()
+                        .payload,
+                    payload
+                );
+
+                // A flipped bit in an escaped byte is caught before decoding
+                let mut corrupted = bytes.clone();
+                corrupted[4] ^= 0x01;
+                assert_eq!(
+                    Packet::verify_frame(&corrupted, &config),
+                    Err(SimpError::ChecksumMismatch)
+                );
+                assert!(Packet::from_bytes_with_config(&corrupted, &config).is_err());
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_roundtrip() {
+        let payload = vec![0x01, START_BYTE, ESCAPE_BYTE, END_BYTE, 0x05];
+        let packet = Packet::new_with_checksum(payload.clone(), ChecksumKind::Crc16);
+        let json = serde_json::to_string(&packet).unwrap();
+        assert_eq!(
+            json,
+            r#"{"payload":[1,126,125,127,5],"checksum_kind":"Crc16","framing":"Escaped"}"#
+        );
+
+        let deserialized: Packet = serde_json::from_str(&json).unwrap();
+        assert!(packet.diff(&deserialized).is_empty());
+        assert_eq!(deserialized.to_bytes(), packet.to_bytes());
+
+        // A received packet holds the unescaped payload and serializes the same way
+        let received =
+            Packet::from_bytes_with_checksum(&packet.to_bytes(), ChecksumKind::Crc16).unwrap();
+        assert_eq!(serde_json::to_string(&received).unwrap(), json);
+
+        // The length and checksum are not taken from the input
+        let deserialized: Packet = serde_json::from_str(
+            r#"{"payload":[1,2,3],"checksum_kind":"Sum8","framing":"LengthPrefixed"}"#,
+        )
+        .unwrap();
+        assert_eq!(deserialized.length, 3);
+        assert_eq!(deserialized.checksum, 6);
+    }
+
+    #[test]
+    fn test_length_prefixed_roundtrip_control_bytes() {
+        let config = ProtocolConfig {
+            framing: Framing::LengthPrefixed,
+            ..Default::default()
+        };
+        let payload = vec![START_BYTE, END_BYTE, ESCAPE_BYTE, 0x00, START_BYTE];
+        let bytes = Packet::new_with_config(payload.clone(), &config).to_bytes();
+
+        // Start byte, two length bytes, the payload as is and the checksum
+        let mut expected = vec![START_BYTE, 5, 0];
+        expected.extend(&payload);
+        expected.push(Packet::calculate_checksum(&payload));
+        assert_eq!(bytes, expected);
+
+        assert_eq!(roundtrip_with_config(&payload, &config).unwrap(), payload);
+        let parsed = Packet::from_bytes_with_config(&bytes, &config).unwrap();
+        assert_eq!(parsed.escape_overhead(), 0);
+        assert!(Packet::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_length_prefixed_long_payload() {
+        let config = ProtocolConfig {
+            framing: Framing::LengthPrefixed,
+            checksum_kind: ChecksumKind::Crc16,
+            ..Default::default()
+        };
+        let payload: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let bytes = Packet::new_with_config(payload.clone(), &config).to_bytes();
+        assert_eq!(&bytes[1..3], &1000u16.to_le_bytes());
+        assert_eq!(Packet::frame_len(&bytes[..3], &config), Some(bytes.len()));
+
+        assert_eq!(roundtrip_with_config(&payload, &config).unwrap(), payload);
+        assert_eq!(
+            Packet::from_bytes_with_config(&bytes[..bytes.len() - 1], &config).err(),
+            Some(SimpError::LengthMismatch(LengthMismatch {
+                declared: 1000,
+                observed: 999,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_long_packet_roundtrip() {
+        // 256 bytes, several of which need escaping, and the largest payload that fits
+        let mut short: Vec<u8> = (0..=255).collect();
+        short[0] = START_BYTE;
+        let longest = vec![0x55; u16::MAX as usize];
+        let config = ProtocolConfig {
+            framing: Framing::EscapedLong,
+            ..Default::default()
+        };
+
+        for payload in [short, longest] {
+            let packet = Packet::new_long(payload.clone());
+            let bytes = packet.to_bytes();
+            assert_eq!(&bytes[1..3], &packet.length.to_le_bytes());
+            assert_eq!(bytes.len(), packet.length as usize + 5);
+
+            assert_eq!(roundtrip_with_config(&payload, &config).unwrap(), payload);
+            let parsed = Packet::from_bytes_long(&bytes).unwrap();
+            assert_eq!(parsed.length, packet.length);
+        }
+        assert_eq!(
+            Packet::new_long(vec![0x55; u16::MAX as usize]).length,
+            u16::MAX
+        );
+    }
+
+    #[test]
+    fn test_default_framing_at_256_bytes() {
+        let longest = Packet::new(vec![0x01; 255]);
+        assert_eq!(longest.to_bytes()[1], 255);
+
+        let mut uart = crate::mocks::MockUart::new();
+        assert_eq!(
+            crate::uart::send_packet(&mut uart, &Packet::new(vec![0x01; 256])).err(),
+            Some(SimpError::PayloadTooLarge)
+        );
+        // Escaping counts towards the limit
+        assert_eq!(
+            crate::uart::send_packet(&mut uart, &Packet::new(vec![START_BYTE; 128])).err(),
+            Some(SimpError::PayloadTooLarge)
+        );
+        assert!(uart.get_written_data().is_empty());
+    }
+
+    #[test]
+    fn test_oversized_payload_is_checkable() {
+        // Fits raw, but not once every byte is escaped
+        let payload = vec![START_BYTE; 200];
+        assert_eq!(
+            Packet::try_new(payload.clone()).err(),
+            Some(SimpError::PayloadTooLarge)
+        );
+        let packet = Packet::new(payload);
+        assert_eq!(
+            packet.try_to_bytes().err(),
+            Some(SimpError::PayloadTooLarge)
+        );
+
+        // The length saturates instead of wrapping, and the receiver rejects the frame
+        let bytes = packet.to_bytes();
+        assert_eq!(bytes[1], u8::MAX);
+        assert!(matches!(
+            Packet::from_bytes(&bytes),
+            Err(SimpError::LengthMismatch(_))
+        ));
+
+        assert!(Packet::try_new(vec![START_BYTE; 127]).is_ok());
+    }
+
+    #[test]
+    fn test_oversized_long_payload_is_checkable() {
+        let config = ProtocolConfig {
+            framing: Framing::EscapedLong,
+            ..Default::default()
+        };
+        let payload = vec![0x01; u16::MAX as usize + 1];
+        assert_eq!(
+            Packet::try_new_with_config(payload.clone(), &config).err(),
+            Some(SimpError::PayloadTooLarge)
+        );
+        let packet = Packet::new_long(payload);
+        assert_eq!(packet.length, u16::MAX);
+        assert_eq!(
+            packet.try_to_bytes().err(),
+            Some(SimpError::PayloadTooLarge)
+        );
+    }
+
+    #[test]
+    fn test_long_packet_truncated() {
+        let bytes = Packet::new_long(vec![0x01; 300]).to_bytes();
+
+        // Cut inside the payload, keeping a valid looking end byte
+        let mut truncated = bytes[..200].to_vec();
+        truncated.push(END_BYTE);
+        assert_eq!(
+            Packet::from_bytes_long(&truncated).err(),
+            Some(SimpError::LengthMismatch(LengthMismatch {
+                declared: 300,
+                observed: 196,
+            }))
+        );
+        // Cut inside the length field
+        assert_eq!(
+            Packet::from_bytes_long(&bytes[..2]).err(),
+            Some(SimpError::InvalidStructure)
+        );
     }
 }
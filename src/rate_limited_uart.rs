@@ -0,0 +1,82 @@
+use crate::clock::Clock;
+use crate::error::SimpError;
+use crate::uart::Uart;
+use core::time::Duration;
+
+/// UART wrapper that keeps consecutive writes at least a minimum interval apart
+///
+/// Some devices overrun when frames arrive back to back. A write that comes too soon
+/// after the previous one waits out the rest of the interval first. Reads pass through
+/// untouched.
+pub struct RateLimitedUart<U: Uart, C: Clock> {
+    inner: U,
+    clock: C,
+    min_interval: Duration,
+    last_write: Option<Duration>,
+}
+
+impl<U: Uart, C: Clock> RateLimitedUart<U, C> {
+    /// Creates a wrapper spacing writes to `inner` at least `min_interval` apart on `clock`.
+    pub fn new(inner: U, clock: C, min_interval: Duration) -> Self {
+        Self {
+            inner,
+            clock,
+            min_interval,
+            last_write: None,
+        }
+    }
+
+    /// Consumes the wrapper and returns the inner UART.
+    pub fn into_inner(self) -> U {
+        self.inner
+    }
+}
+
+impl<U: Uart, C: Clock> Uart for RateLimitedUart<U, C> {
+    fn write(&mut self, data: &[u8]) -> Result<usize, SimpError> {
+        if let Some(last_write) = self.last_write {
+            let elapsed = self.clock.now() - last_write;
+            if elapsed < self.min_interval {
+                self.clock.sleep(self.min_interval - elapsed);
+            }
+        }
+        self.last_write = Some(self.clock.now());
+        self.inner.write(data)
+    }
+
+    fn read(&mut self) -> Option<u8> {
+        self.inner.read()
+    }
+
+    fn try_read(&mut self) -> Result<Option<u8>, SimpError> {
+        self.inner.try_read()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::mocks::MockUart;
+
+    #[test]
+    fn test_writes_are_spaced() {
+        let clock = MockClock::new();
+        let mut uart = RateLimitedUart::new(MockUart::new(), &clock, Duration::from_millis(10));
+
+        uart.write(&[0x01]).unwrap();
+        assert_eq!(clock.now(), Duration::ZERO);
+
+        // Too soon, waits out the rest of the interval
+        clock.advance(Duration::from_millis(4));
+        uart.write(&[0x02]).unwrap();
+        assert_eq!(clock.now(), Duration::from_millis(10));
+
+        // Already far enough apart, no wait
+        clock.advance(Duration::from_millis(25));
+        uart.write(&[0x03]).unwrap();
+        assert_eq!(clock.now(), Duration::from_millis(35));
+
+        assert_eq!(uart.into_inner().get_written_data(), vec![0x01, 0x02, 0x03]);
+    }
+}
@@ -0,0 +1,342 @@
+use crate::config::ProtocolConfig;
+use crate::error::SimpError;
+use crate::packet::Packet;
+use crate::uart::{classify_packet, PacketKind, Uart};
+
+/// Decoder that assembles frames from bytes handed over in arbitrary pieces
+///
+/// Bytes can arrive split across several buffers, for example one per DMA transfer. Each
+/// piece is fed in as it arrives, and frames that straddle the edges of the pieces are
+/// put back together without the caller copying everything into one buffer first.
+pub struct FrameDecoder {
+    config: ProtocolConfig,
+    pending: Vec<u8>,
+}
+
+impl FrameDecoder {
+    /// Creates a decoder with no pending bytes.
+    pub fn new(config: ProtocolConfig) -> Self {
+        Self {
+            config,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Bytes fed in that are not part of a returned packet yet.
+    pub fn pending(&self) -> &[u8] {
+        &self.pending
+    }
+
+    /// Feeds in the next piece of the byte stream and yields the packets it completes.
+    pub fn feed<'a>(
+        &'a mut self,
+        bytes: &[u8],
+    ) -> impl Iterator<Item = Result<Packet, SimpError>> + 'a {
+        self.pending.extend_from_slice(bytes);
+        std::iter::from_fn(move || self.next_packet())
+    }
+
+    /// Takes the next complete frame off the pending bytes and decodes it.
+    ///
+    /// Fails with `SimpError::FrameTooLarge` if more than `max_frame_size` bytes are
+    /// pending without a complete frame, dropping them.
+    fn next_packet(&mut self) -> Option<Result<Packet, SimpError>> {
+        let frame_end = if self.config.framing.is_escaped() {
+            self.pending
+                .iter()
+                .position(|&byte| byte == self.config.end_byte)
+                .map(|end| end + 1)
+        } else {
+            self.next_length_prefixed_end()
+        };
+        if let Some(end) = frame_end {
+            let rest = self.pending.split_off(end);
+            let frame = std::mem::replace(&mut self.pending, rest);
+            return Some(Packet::from_bytes_with_config(&frame, &self.config));
+        }
+
+        if self.is_overflowing() {
+            self.pending.clear();
+            return Some(Err(SimpError::FrameTooLarge));
+        }
+        None
+    }
+
+    /// Finds where the pending length-prefixed frame ends, once all of it is pending.
+    ///
+    /// Bytes before the first start byte cannot begin a frame and are dropped.
+    fn next_length_prefixed_end(&mut self) -> Option<usize> {
+        let start = self
+            .pending
+            .iter()
+            .position(|&byte| byte == self.config.start_byte)
+            .unwrap_or(self.pending.len());
+        self.pending.drain(..start);
+        Packet::frame_len(&self.pending, &self.config).filter(|&len| len <= self.pending.len())
+    }
+
+    /// Returns whether the byte just added to the pending bytes completes a frame.
+    fn completes_frame(&self, byte: u8) -> bool {
+        if self.config.framing.is_escaped() {
+            return byte == self.config.end_byte;
+        }
+        let Some(start) = self
+            .pending
+            .iter()
+            .position(|&byte| byte == self.config.start_byte)
+        else {
+            return false;
+        };
+        let frame = &self.pending[start..];
+        Packet::frame_len(frame, &self.config).is_some_and(|len| len <= frame.len())
+    }
+
+    fn is_overflowing(&self) -> bool {
+        self.config
+            .max_frame_size
+            .is_some_and(|max| self.pending.len() > max)
+    }
+}
+
+/// Receiver that keeps a partly read frame for the next receive
+///
+/// `receive_packet` drops whatever it read when the UART runs out of data before the end
+/// byte. This receiver holds on to those bytes, so a frame that arrives across several
+/// calls is still returned. Reading stops at the end of a frame, leaving back-to-back
+/// frames on the UART for the following receives.
+pub struct PacketReceiver {
+    decoder: FrameDecoder,
+}
+
+impl PacketReceiver {
+    /// Creates a receiver with no pending bytes.
+    pub fn new(config: ProtocolConfig) -> Self {
+        Self {
+            decoder: FrameDecoder::new(config),
+        }
+    }
+
+    /// Bytes read from the UART that are not part of a returned packet yet.
+    pub fn pending(&self) -> &[u8] {
+        self.decoder.pending()
+    }
+
+    /// Receives the next packet, reading from the UART until a frame is complete.
+    ///
+    /// Pending bytes are used first. Fails with `SimpError::FrameTooLarge` if more than
+    /// `max_frame_size` bytes are pending without a complete frame, dropping them.
+    pub fn receive(&mut self, uart: &mut impl Uart) -> Result<Packet, SimpError> {
+        loop {
+            if let Some(result) = self.decoder.next_packet() {
+                return result;
+            }
+
+            // Read up to the end of the next frame, so a busy line can't grow it forever
+            let read_before = self.decoder.pending.len();
+            while let Some(byte) = uart.try_read()? {
+                self.decoder.pending.push(byte);
+                if self.decoder.is_overflowing() || self.decoder.completes_frame(byte) {
+                    break;
+                }
+            }
+            if self.decoder.pending.len() == read_before {
+                return Err(SimpError::Timeout);
+            }
+        }
+    }
+}
+
+/// Result of polling a `MultiPacketReceiver`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Progress {
+    /// The transfer is still going, poll again once more data may have arrived
+    Incomplete,
+    /// The transfer finished with this data
+    Complete(Vec<u8>),
+}
+
+/// Multi-packet receive that can be spread across several calls
+///
+/// Each `poll` takes whatever has arrived and returns without waiting for the rest, which
+/// suits single-threaded loops that have other work to do between polls. A partially
+/// received frame is kept until the rest of it arrives.
+pub struct MultiPacketReceiver {
+    config: ProtocolConfig,
+    frames: PacketReceiver,
+    data: Vec<u8>,
+    received_packets: usize,
+    started: bool,
+}
+
+impl MultiPacketReceiver {
+    /// Creates a receiver waiting for the first packet of a transfer.
+    pub fn new(config: ProtocolConfig) -> Self {
+        Self {
+            config,
+            frames: PacketReceiver::new(config),
+            data: Vec::new(),
+            received_packets: 0,
+            started: !config.transfer_start,
+        }
+    }
+
+    /// Number of packets received so far in the current transfer.
+    pub fn received_packets(&self) -> usize {
+        self.received_packets
+    }
+
+    /// Receives the packets that have arrived so far.
+    ///
+    /// Once the transfer completes its data is returned and the receiver is ready for the
+    /// next transfer. After an error the receiver should be replaced, since the transfer
+    /// can't continue in sequence.
+    pub fn poll(&mut self, uart: &mut impl Uart) -> Result<Progress, SimpError> {
+        loop {
+            if self
+                .config
+                .max_packets
+                .is_some_and(|max| self.received_packets >= max)
+            {
+                return Err(SimpError::TooManyPackets);
+            }
+
+            let packet = match self.frames.receive(uart) {
+                Ok(packet) => packet,
+                Err(SimpError::Timeout) => return Ok(Progress::Incomplete),
+                Err(error) => return Err(error),
+            };
+            match classify_packet(
+                &packet,
+                self.received_packets,
+                &mut self.started,
+                &self.config,
+            )? {
+                PacketKind::Skipped => {}
+                PacketKind::Chunk { data, last } => {
+                    self.data.extend_from_slice(data);
+                    self.received_packets += 1;
+                    if last {
+                        self.received_packets = 0;
+                        self.started = !self.config.transfer_start;
+                        return Ok(Progress::Complete(std::mem::take(&mut self.data)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mocks::{multi_packet_stream, MockUart};
+    use crate::packet::{Framing, END_BYTE, START_BYTE};
+
+    #[test]
+    fn test_back_to_back_frames() {
+        let first = Packet::new(vec![0x01, 0x02, 0x03]);
+        let second = Packet::new(vec![0x04, 0x05]);
+        let mut stream = first.to_bytes();
+        stream.extend(second.to_bytes());
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(stream);
+        let mut receiver = PacketReceiver::new(ProtocolConfig::default());
+
+        assert_eq!(receiver.receive(&mut uart).unwrap().payload, first.payload);
+        // Reading stopped at the end byte, leaving the second frame on the UART
+        assert!(receiver.pending().is_empty());
+
+        assert_eq!(receiver.receive(&mut uart).unwrap().payload, second.payload);
+        assert!(receiver.pending().is_empty());
+        assert!(receiver.receive(&mut uart).is_err());
+    }
+
+    #[test]
+    fn test_receive_frame_split_across_calls() {
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+        let bytes = packet.to_bytes();
+        let mut uart = MockUart::new();
+        let mut receiver = PacketReceiver::new(ProtocolConfig::default());
+
+        uart.set_read_data(bytes[..3].to_vec());
+        assert_eq!(receiver.receive(&mut uart).err(), Some(SimpError::Timeout));
+        assert_eq!(receiver.pending(), &bytes[..3]);
+
+        // A continuous stream behind the frame is left unread
+        let mut rest = bytes[3..].to_vec();
+        rest.extend(vec![0x00; 1000]);
+        uart.set_read_data(rest);
+        assert_eq!(receiver.receive(&mut uart).unwrap().payload, packet.payload);
+        assert!(receiver.pending().is_empty());
+        assert_eq!(uart.read(), Some(0x00));
+    }
+
+    #[test]
+    fn test_multi_packet_receiver_across_polls() {
+        let data: Vec<u8> = (0..600).map(|i| (i % 100) as u8).collect();
+        let stream = multi_packet_stream(&data, &ProtocolConfig::default());
+
+        let mut uart = MockUart::new();
+        let mut receiver = MultiPacketReceiver::new(ProtocolConfig::default());
+        assert_eq!(receiver.poll(&mut uart), Ok(Progress::Incomplete));
+
+        // Deliver the stream in pieces that split frames
+        let mut pieces = stream.chunks(100).peekable();
+        while let Some(piece) = pieces.next() {
+            uart.set_read_data(piece.to_vec());
+            let progress = receiver.poll(&mut uart).unwrap();
+            if pieces.peek().is_some() {
+                assert_eq!(progress, Progress::Incomplete);
+            } else {
+                assert_eq!(progress, Progress::Complete(data.clone()));
+            }
+        }
+        assert_eq!(receiver.received_packets(), 0);
+    }
+
+    #[test]
+    fn test_frame_decoder_across_slices() {
+        let packet = Packet::new(vec![0x01, START_BYTE, 0x03, 0x04]);
+        let bytes = packet.to_bytes();
+        let mut decoder = FrameDecoder::new(ProtocolConfig::default());
+
+        // Split inside the escape sequence and just before the end byte
+        assert_eq!(decoder.feed(&bytes[..4]).count(), 0);
+        assert_eq!(decoder.feed(&bytes[4..bytes.len() - 1]).count(), 0);
+        let packets: Vec<_> = decoder.feed(&bytes[bytes.len() - 1..]).collect();
+
+        assert_eq!(packets.len(), 1);
+        assert_eq!(
+            packets[0].as_ref().unwrap().payload,
+            vec![0x01, START_BYTE, 0x03, 0x04]
+        );
+        assert!(decoder.pending().is_empty());
+    }
+
+    #[test]
+    fn test_length_prefixed_frames_across_slices() {
+        let config = ProtocolConfig {
+            framing: Framing::LengthPrefixed,
+            ..Default::default()
+        };
+        let first = Packet::new_with_config(vec![END_BYTE, START_BYTE, END_BYTE], &config);
+        let second = Packet::new_with_config(vec![0x01, END_BYTE], &config);
+        // Line noise before the first frame is skipped up to the start byte
+        let mut stream = vec![0x00, END_BYTE];
+        stream.extend(first.to_bytes());
+        stream.extend(second.to_bytes());
+        let mut decoder = FrameDecoder::new(config);
+
+        assert_eq!(decoder.feed(&stream[..4]).count(), 0);
+        let packets: Vec<_> = decoder.feed(&stream[4..]).collect();
+
+        assert_eq!(packets.len(), 2);
+        assert_eq!(
+            packets[0].as_ref().unwrap().payload,
+            vec![END_BYTE, START_BYTE, END_BYTE]
+        );
+        assert_eq!(packets[1].as_ref().unwrap().payload, vec![0x01, END_BYTE]);
+        assert!(decoder.pending().is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+use crate::clock::Clock;
+use core::time::Duration;
+
+/// Decides whether and when to retry after a failed attempt
+///
+/// `attempt` counts the failed attempts so far, starting at 1 after the first one.
+/// Returning None stops retrying.
+pub trait RetryPolicy {
+    fn next_delay(&mut self, attempt: usize) -> Option<Duration>;
+}
+
+/// Retries a fixed number of times with the same delay before each retry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedRetries {
+    /// Number of retries after the first attempt
+    pub retries: usize,
+    /// Delay before each retry
+    pub delay: Duration,
+}
+
+impl RetryPolicy for FixedRetries {
+    fn next_delay(&mut self, attempt: usize) -> Option<Duration> {
+        (attempt <= self.retries).then_some(self.delay)
+    }
+}
+
+/// Retries with a delay that doubles after every failed attempt, up to a maximum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExponentialBackoff {
+    /// Number of retries after the first attempt
+    pub retries: usize,
+    /// Delay before the first retry
+    pub initial_delay: Duration,
+    /// Upper bound on the delay
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn next_delay(&mut self, attempt: usize) -> Option<Duration> {
+        if attempt > self.retries {
+            return None;
+        }
+        let shift = u32::try_from(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+        let factor = 1u32.checked_shl(shift).unwrap_or(u32::MAX);
+        Some(
+            self.initial_delay
+                .saturating_mul(factor)
+                .min(self.max_delay),
+        )
+    }
+}
+
+/// Retries with a fixed delay until a deadline measured on the given clock has passed
+///
+/// The deadline counts from when the policy is created. A retry is only scheduled if it
+/// would start before the deadline.
+pub struct DeadlineBased<'a, C: Clock> {
+    clock: &'a C,
+    started: Duration,
+    deadline: Duration,
+    delay: Duration,
+}
+
+impl<'a, C: Clock> DeadlineBased<'a, C> {
+    /// Creates a policy that stops retrying once `deadline` has passed on `clock`.
+    pub fn new(clock: &'a C, deadline: Duration, delay: Duration) -> Self {
+        Self {
+            clock,
+            started: clock.now(),
+            deadline,
+            delay,
+        }
+    }
+}
+
+impl<C: Clock> RetryPolicy for DeadlineBased<'_, C> {
+    fn next_delay(&mut self, _attempt: usize) -> Option<Duration> {
+        let elapsed = self.clock.now() - self.started;
+        (elapsed + self.delay < self.deadline).then_some(self.delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    fn delays(policy: &mut impl RetryPolicy) -> Vec<Duration> {
+        (1..)
+            .map_while(|attempt| policy.next_delay(attempt))
+            .collect()
+    }
+
+    #[test]
+    fn test_fixed_retries() {
+        let mut policy = FixedRetries {
+            retries: 3,
+            delay: Duration::from_millis(10),
+        };
+        assert_eq!(delays(&mut policy), vec![Duration::from_millis(10); 3]);
+    }
+
+    #[test]
+    fn test_exponential_backoff() {
+        let mut policy = ExponentialBackoff {
+            retries: 5,
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(50),
+        };
+        let expected: Vec<Duration> = [10, 20, 40, 50, 50]
+            .into_iter()
+            .map(Duration::from_millis)
+            .collect();
+        assert_eq!(delays(&mut policy), expected);
+    }
+
+    #[test]
+    fn test_exponential_backoff_extreme_attempts() {
+        let mut policy = ExponentialBackoff {
+            retries: usize::MAX,
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_secs(1),
+        };
+        assert_eq!(policy.next_delay(0), Some(Duration::from_millis(10)));
+        assert_eq!(policy.next_delay(usize::MAX), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_deadline_based() {
+        let clock = MockClock::new();
+        let mut policy = DeadlineBased::new(
+            &clock,
+            Duration::from_millis(100),
+            Duration::from_millis(20),
+        );
+
+        let mut attempt = 0;
+        loop {
+            // Each attempt takes 10ms before failing
+            clock.advance(Duration::from_millis(10));
+            attempt += 1;
+            match policy.next_delay(attempt) {
+                Some(delay) => clock.sleep(delay),
+                None => break,
+            }
+        }
+        // 30ms per failed attempt and delay, and a retry must start before the deadline
+        assert_eq!(attempt, 4);
+        assert_eq!(clock.now(), Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,106 @@
+use crate::error::SimpError;
+use crate::uart::Uart;
+use std::io::Write;
+use std::time::Instant;
+
+/// UART wrapper that logs every byte in and out while passing it through
+///
+/// Each write is logged as a `TX` line and each byte read as an `RX` line. Lines are
+/// prefixed with the time elapsed since the wrapper was created and hold the bytes in hex:
+///
+/// ```text
+/// [    0.000412] TX 7E 03 01 02 03 06 7F
+/// [    0.001873] RX 06
+/// ```
+///
+/// Failures to write the log are ignored so that logging never breaks the link.
+pub struct TeeUart<U: Uart, W: Write> {
+    inner: U,
+    log: W,
+    start_time: Instant,
+}
+
+impl<U: Uart, W: Write> TeeUart<U, W> {
+    /// Creates a new tee around the given UART, logging to the given writer.
+    pub fn new(inner: U, log: W) -> Self {
+        Self {
+            inner,
+            log,
+            start_time: Instant::now(),
+        }
+    }
+
+    /// Consumes the tee and returns the inner UART and the log writer.
+    pub fn into_parts(self) -> (U, W) {
+        (self.inner, self.log)
+    }
+
+    fn record(&mut self, direction: &str, data: &[u8]) {
+        let elapsed = self.start_time.elapsed().as_secs_f64();
+        let _ = write!(self.log, "[{:12.6}] {}", elapsed, direction);
+        for byte in data {
+            let _ = write!(self.log, " {:02X}", byte);
+        }
+        let _ = writeln!(self.log);
+    }
+}
+
+impl<U: Uart, W: Write> Uart for TeeUart<U, W> {
+    fn write(&mut self, data: &[u8]) -> Result<usize, SimpError> {
+        let written = self.inner.write(data)?;
+        self.record("TX", &data[..written]);
+        Ok(written)
+    }
+
+    fn read(&mut self) -> Option<u8> {
+        let byte = self.inner.read()?;
+        self.record("RX", &[byte]);
+        Some(byte)
+    }
+
+    fn try_read(&mut self) -> Result<Option<u8>, SimpError> {
+        let byte = self.inner.try_read()?;
+        if let Some(byte) = byte {
+            self.record("RX", &[byte]);
+        }
+        Ok(byte)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mocks::MockUart;
+    use crate::packet::Packet;
+    use crate::uart::{receive_packet, send_packet};
+
+    fn logged_bytes(log: &str, direction: &str) -> Vec<u8> {
+        log.lines()
+            .filter_map(|line| line.split("] ").nth(1))
+            .filter_map(|entry| entry.strip_prefix(direction))
+            .flat_map(|hex| hex.split_whitespace())
+            .map(|byte| u8::from_str_radix(byte, 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_tee_records_traffic() {
+        let outgoing = Packet::new(vec![0x01, 0x02, 0x03]);
+        let incoming = Packet::new(vec![0x04, 0x05]);
+
+        let uart = MockUart::new();
+        uart.set_read_data(incoming.to_bytes());
+        let mut tee = TeeUart::new(uart, Vec::new());
+
+        send_packet(&mut tee, &outgoing).unwrap();
+        let received = receive_packet(&mut tee).unwrap();
+        assert_eq!(received.payload, incoming.payload);
+
+        let (uart, log) = tee.into_parts();
+        assert_eq!(uart.get_written_data(), outgoing.to_bytes());
+
+        let log = String::from_utf8(log).unwrap();
+        assert_eq!(logged_bytes(&log, "TX"), outgoing.to_bytes());
+        assert_eq!(logged_bytes(&log, "RX"), incoming.to_bytes());
+    }
+}
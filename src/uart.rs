@@ -1,368 +1,2564 @@
+#[cfg(any(feature = "std", feature = "heapless"))]
+use crate::buffer::ReassemblyBuffer;
+#[cfg(feature = "std")]
+use crate::buffer::SliceBuffer;
+#[cfg(feature = "std")]
+use crate::clock::{Clock, SystemClock};
+#[cfg(any(feature = "std", feature = "heapless"))]
+use crate::config::ProtocolConfig;
+#[cfg(feature = "std")]
+use crate::config::SequenceWidth;
+use crate::error::SimpError;
+#[cfg(any(feature = "std", feature = "heapless"))]
+use crate::packet::frame_len;
+#[cfg(feature = "std")]
 use crate::packet::Packet;
-use std::time::{Duration, Instant};
-
-const ACK_BYTE: u8 = 0x06;
-const NACK_BYTE: u8 = 0x15;
+#[cfg(feature = "std")]
+use crate::retry::RetryPolicy;
+#[cfg(feature = "std")]
+use std::io::Write;
+#[cfg(feature = "std")]
+use std::time::Duration;
+
+/// Byte a receiver replies with to acknowledge a packet
+pub const ACK_BYTE: u8 = 0x06;
+/// Byte a receiver replies with to reject a corrupt packet
+pub const NACK_BYTE: u8 = 0x15;
+#[cfg(feature = "std")]
+const RESEND_BYTE: u8 = 0x12;
+
+/// Payload of the frame that marks the start of a multi-packet transfer
+///
+/// Only sent and recognized when `ProtocolConfig::transfer_start` is set.
+pub const TRANSFER_START: [u8; 3] = *b"SOT";
+
+/// Default max size for the payload part of a packet in multi-packet transfers, including the sequence byte
+pub const MAX_PAYLOAD_SIZE: usize = 250;
 
 /// Trait for UART communication
 /// 
 /// This trait needs to be implemented in order for the library to work.
 /// All functions depend on the implementation of this trait.
 pub trait Uart {
-    fn write(&mut self, data: &[u8]) -> Result<usize, &'static str>;
+    /// Writes the data, returning the number of bytes written
+    ///
+    /// Report a device error as `SimpError::Uart` with a message; the send functions pass
+    /// it on as is.
+    fn write(&mut self, data: &[u8]) -> Result<usize, SimpError>;
+    fn read(&mut self) -> Option<u8>;
+
+    /// Reads a byte, telling a hard device error apart from no data being available
+    ///
+    /// `Ok(None)` means nothing is available right now. The receive functions use this
+    /// and abort on an error instead of waiting for data that will never come. Return
+    /// `SimpError::ConnectionClosed` once the other end is gone, such as at the end of a
+    /// file or on a closed socket. The default never fails; override it when the device
+    /// can report errors.
+    fn try_read(&mut self) -> Result<Option<u8>, SimpError> {
+        Ok(self.read())
+    }
+}
+
+/// Trait for the receiving half of a UART
+///
+/// Implement this and `Writer` instead of `Uart` when a transport has separate TX and RX
+/// handles. Anything implementing both is a `Uart`.
+pub trait Reader {
     fn read(&mut self) -> Option<u8>;
+
+    /// Reads a byte, telling a hard device error apart from no data being available
+    fn try_read(&mut self) -> Result<Option<u8>, SimpError> {
+        Ok(self.read())
+    }
+}
+
+/// Trait for the sending half of a UART
+pub trait Writer {
+    /// Writes the data, returning the number of bytes written
+    fn write(&mut self, data: &[u8]) -> Result<usize, SimpError>;
+}
+
+impl<T: Reader + Writer> Uart for T {
+    fn write(&mut self, data: &[u8]) -> Result<usize, SimpError> {
+        Writer::write(self, data)
+    }
+
+    fn read(&mut self) -> Option<u8> {
+        Reader::read(self)
+    }
+
+    fn try_read(&mut self) -> Result<Option<u8>, SimpError> {
+        Reader::try_read(self)
+    }
+}
+
+/// UART made of separately borrowed reader and writer halves
+///
+/// Lets split halves be used with the functions that both send and receive, such as
+/// `send_packet_with_ack`.
+pub struct SplitUart<'a, R: Reader, W: Writer> {
+    reader: &'a mut R,
+    writer: &'a mut W,
+}
+
+impl<'a, R: Reader, W: Writer> SplitUart<'a, R, W> {
+    /// Joins the given halves into one UART.
+    pub fn new(reader: &'a mut R, writer: &'a mut W) -> Self {
+        Self { reader, writer }
+    }
+}
+
+impl<R: Reader, W: Writer> Reader for SplitUart<'_, R, W> {
+    fn read(&mut self) -> Option<u8> {
+        self.reader.read()
+    }
+
+    fn try_read(&mut self) -> Result<Option<u8>, SimpError> {
+        self.reader.try_read()
+    }
+}
+
+impl<R: Reader, W: Writer> Writer for SplitUart<'_, R, W> {
+    fn write(&mut self, data: &[u8]) -> Result<usize, SimpError> {
+        self.writer.write(data)
+    }
+}
+
+/// Exposes a whole UART as either half
+#[cfg(feature = "std")]
+struct Halves<'a, U: Uart>(&'a mut U);
+
+#[cfg(feature = "std")]
+impl<U: Uart> Reader for Halves<'_, U> {
+    fn read(&mut self) -> Option<u8> {
+        self.0.read()
+    }
+
+    fn try_read(&mut self) -> Result<Option<u8>, SimpError> {
+        self.0.try_read()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<U: Uart> Writer for Halves<'_, U> {
+    fn write(&mut self, data: &[u8]) -> Result<usize, SimpError> {
+        self.0.write(data)
+    }
 }
 
 /// Function to send a packet without waiting for an ACK
-pub fn send_packet(uart: &mut impl Uart, packet: &Packet) -> Result<usize, &'static str> {
-    uart.write(&packet.to_bytes())
-        .map_err(|_| "Failed to send packet")
+///
+/// A write that succeeds without writing anything means the device is stuck, so it is
+/// reported as an error rather than a successful send. A packet whose escaped payload
+/// does not fit the length byte is rejected with `SimpError::PayloadTooLarge` before
+/// anything is written.
+#[cfg(feature = "std")]
+pub fn send_packet(uart: &mut impl Uart, packet: &Packet) -> Result<usize, SimpError> {
+    send_packet_to_writer(&mut Halves(uart), packet)
+}
+
+/// Function to send a packet through the sending half of a UART
+#[cfg(feature = "std")]
+pub fn send_packet_to_writer(
+    writer: &mut impl Writer,
+    packet: &Packet,
+) -> Result<usize, SimpError> {
+    match writer.write(&packet.try_to_bytes()?) {
+        Ok(0) => Err(SimpError::WriteStalled),
+        Ok(written) => Ok(written),
+        Err(error) => Err(error),
+    }
 }
 
 /// Function to send a packet and wait for an ACK
+#[cfg(feature = "std")]
 pub fn send_packet_with_ack(
     uart: &mut impl Uart,
     packet: &Packet,
     retries: usize,
     timeout: Duration,
-) -> Result<(), &'static str> {
-    for _ in 0..retries {
+) -> Result<(), SimpError> {
+    send_packet_with_ack_and_backoff(uart, packet, retries, timeout, Duration::ZERO)
+}
+
+/// Function to send a packet and wait for an ACK, backing off after a timeout
+///
+/// A NACK means the receiver saw a bad frame and is ready for another one, so the packet
+/// is resent immediately. A timeout may mean the receiver is busy, so the sender waits
+/// `backoff` before resending. Both consume one retry.
+#[cfg(feature = "std")]
+pub fn send_packet_with_ack_and_backoff(
+    uart: &mut impl Uart,
+    packet: &Packet,
+    retries: usize,
+    timeout: Duration,
+    backoff: Duration,
+) -> Result<(), SimpError> {
+    send_packet_with_ack_and_clock(uart, packet, retries, timeout, backoff, &SystemClock::new())
+}
+
+/// Function to send a packet and wait for an ACK, measuring time with the given clock
+///
+/// Behaves like `send_packet_with_ack_and_backoff`. Passing a `MockClock` makes the
+/// timeout and backoff behavior deterministic in tests.
+#[cfg(feature = "std")]
+pub fn send_packet_with_ack_and_clock(
+    uart: &mut impl Uart,
+    packet: &Packet,
+    retries: usize,
+    timeout: Duration,
+    backoff: Duration,
+    clock: &impl Clock,
+) -> Result<(), SimpError> {
+    let timing = AckTiming {
+        timeout,
+        backoff,
+        deadline: None,
+    };
+    send_packet_and_wait(uart, packet, retries, &timing, clock, None, None).map(|_| ())
+}
+
+/// Function to send a packet and wait for an ACK, returning the ACK round-trip time
+///
+/// Behaves like `send_packet_with_ack_and_clock`. The time is measured from the end of
+/// the write of the acknowledged attempt to the arrival of its ACK, so failed attempts
+/// and backoffs are left out. Tools can use it to size the timeout for a link.
+#[cfg(feature = "std")]
+pub fn send_packet_with_ack_rtt(
+    uart: &mut impl Uart,
+    packet: &Packet,
+    retries: usize,
+    timeout: Duration,
+    backoff: Duration,
+    clock: &impl Clock,
+) -> Result<Duration, SimpError> {
+    let timing = AckTiming {
+        timeout,
+        backoff,
+        deadline: None,
+    };
+    match send_packet_and_wait(uart, packet, retries, &timing, clock, None, None)? {
+        Reply::Ack(rtt) => Ok(rtt),
+        // A single packet has no transfer to rewind
+        Reply::Resend(_) => Err(SimpError::InvalidResendRequest),
+    }
+}
+
+/// Function to send a packet and wait for an ACK, giving up once a total deadline passes
+///
+/// Each attempt waits at most `attempt_timeout` for the ACK, which keeps retries quick on
+/// a lossy link, while `deadline` bounds the whole operation including backoffs. Fails
+/// with `SimpError::DeadlineExceeded` if the deadline passes before the packet is
+/// acknowledged.
+#[cfg(feature = "std")]
+pub fn send_packet_with_ack_and_deadline(
+    uart: &mut impl Uart,
+    packet: &Packet,
+    retries: usize,
+    attempt_timeout: Duration,
+    deadline: Duration,
+    backoff: Duration,
+    clock: &impl Clock,
+) -> Result<(), SimpError> {
+    let timing = AckTiming {
+        timeout: attempt_timeout,
+        backoff,
+        deadline: Some(deadline),
+    };
+    send_packet_and_wait(uart, packet, retries, &timing, clock, None, None).map(|_| ())
+}
+
+/// Function to send a payload tagged with a sequence number and wait for a matching ACK
+///
+/// The sequence number is prefixed to the payload in `config.sequence_width` bytes, and
+/// the receiver replies with ACK_BYTE followed by the same bytes, as
+/// `receive_packet_with_sequenced_ack` does. An ACK for any other sequence number is
+/// stale, left over from an earlier send, and does not confirm this one. Callers should
+/// use a different sequence number for each send.
+#[cfg(feature = "std")]
+pub fn send_packet_with_sequenced_ack(
+    uart: &mut impl Uart,
+    payload: &[u8],
+    sequence: usize,
+    retries: usize,
+    timeout: Duration,
+    config: &ProtocolConfig,
+) -> Result<(), SimpError> {
+    send_packet_with_sequenced_ack_and_clock(
+        uart,
+        payload,
+        sequence,
+        retries,
+        timeout,
+        config,
+        &SystemClock::new(),
+    )
+}
+
+/// Function to send a sequenced payload and wait for its ACK, measuring time with the given clock
+///
+/// Behaves like `send_packet_with_sequenced_ack`.
+#[cfg(feature = "std")]
+pub fn send_packet_with_sequenced_ack_and_clock(
+    uart: &mut impl Uart,
+    payload: &[u8],
+    sequence: usize,
+    retries: usize,
+    timeout: Duration,
+    config: &ProtocolConfig,
+    clock: &impl Clock,
+) -> Result<(), SimpError> {
+    let sequence = config.sequence_width.encode(sequence);
+    let mut sequenced = sequence.clone();
+    sequenced.extend_from_slice(payload);
+    let packet = Packet::new_with_config(sequenced, config);
+    let timing = AckTiming {
+        timeout,
+        backoff: Duration::ZERO,
+        deadline: None,
+    };
+    send_packet_and_wait(
+        uart,
+        &packet,
+        retries,
+        &timing,
+        clock,
+        None,
+        Some(&sequence),
+    )
+    .map(|_| ())
+}
+
+/// Function to send a packet and wait for an ACK, retrying as the given policy decides
+///
+/// Each attempt waits up to `timeout` for the ACK. After a NACK or a timeout the policy
+/// is asked for the delay before the next attempt, or whether to give up.
+#[cfg(feature = "std")]
+pub fn send_packet_with_retry_policy(
+    uart: &mut impl Uart,
+    packet: &Packet,
+    timeout: Duration,
+    policy: &mut impl RetryPolicy,
+    clock: &impl Clock,
+) -> Result<(), SimpError> {
+    let timing = AckTiming {
+        timeout,
+        backoff: Duration::ZERO,
+        deadline: None,
+    };
+    let mut attempt = 0;
+    loop {
+        match send_packet_and_wait(uart, packet, 1, &timing, clock, None, None) {
+            Ok(_) => return Ok(()),
+            Err(SimpError::RetriesExhausted) => {}
+            Err(error) => return Err(error),
+        }
+        attempt += 1;
+        match policy.next_delay(attempt) {
+            Some(delay) => clock.sleep(delay),
+            None => return Err(SimpError::RetriesExhausted),
+        }
+    }
+}
+
+/// Timeouts used while waiting for an ACK
+#[cfg(feature = "std")]
+struct AckTiming {
+    /// Time to wait for a reply to each attempt
+    timeout: Duration,
+    /// Time to wait before retrying after a timeout
+    backoff: Duration,
+    /// Time after which no further attempts are made, if any
+    deadline: Option<Duration>,
+}
+
+/// Reply that ended the wait for an ACK
+#[cfg(feature = "std")]
+enum Reply {
+    /// The packet was acknowledged this long after the last attempt was sent
+    Ack(Duration),
+    /// The receiver asked to resend from the packet with this sequence number
+    Resend(usize),
+}
+
+/// Sends a packet and waits for an ACK, retrying on NACK and timeout
+///
+/// When `resend_width` is set, a RESEND_BYTE followed by a sequence number of that width
+/// also ends the wait. When `ack_sequence` is set, only an ACK followed by those bytes
+/// counts, and ACKs carrying any other sequence number are ignored as stale.
+#[cfg(feature = "std")]
+fn send_packet_and_wait(
+    uart: &mut impl Uart,
+    packet: &Packet,
+    retries: usize,
+    timing: &AckTiming,
+    clock: &impl Clock,
+    resend_width: Option<SequenceWidth>,
+    ack_sequence: Option<&[u8]>,
+) -> Result<Reply, SimpError> {
+    let started = clock.now();
+    // Time left before the deadline, or None if there is no deadline
+    let remaining = || {
+        timing
+            .deadline
+            .map(|deadline| deadline.saturating_sub(clock.now() - started))
+    };
+
+    for attempt in 0..retries {
+        if remaining() == Some(Duration::ZERO) {
+            return Err(SimpError::DeadlineExceeded);
+        }
+
         // Send the packet without waiting for ACK
         send_packet(uart, packet)?;
 
-        // Wait for ACK or NACK
-        let start_time = Instant::now();
-        while start_time.elapsed() < timeout {
-            if let Some(response) = uart.read() {
-                if response == ACK_BYTE {
+        // Wait for ACK or NACK, but not past the deadline
+        let timeout = remaining().map_or(timing.timeout, |left| left.min(timing.timeout));
+        let mut nack_received = false;
+        let mut resend_sequence: Option<Vec<u8>> = None;
+        let mut acked_sequence: Option<Vec<u8>> = None;
+        let start_time = clock.now();
+        while clock.now() - start_time < timeout {
+            if let Some(response) = uart.try_read()? {
+                if let Some(sequence) = resend_sequence.as_mut() {
+                    sequence.push(response);
+                    if Some(sequence.len()) == resend_width.map(SequenceWidth::bytes) {
+                        // Resend request received, rewind
+                        return Ok(Reply::Resend(SequenceWidth::decode(sequence)));
+                    }
+                } else if let Some(sequence) = acked_sequence.as_mut() {
+                    sequence.push(response);
+                    if Some(sequence.as_slice()) == ack_sequence {
+                        // ACK for this packet received, success
+                        return Ok(Reply::Ack(clock.now() - start_time));
+                    }
+                    if Some(sequence.len()) == ack_sequence.map(<[u8]>::len) {
+                        // Stale ACK for an earlier packet, keep waiting
+                        acked_sequence = None;
+                    }
+                } else if response == ACK_BYTE && ack_sequence.is_some() {
+                    // ACK, the sequence it confirms follows
+                    acked_sequence = Some(Vec::new());
+                } else if response == ACK_BYTE {
                     // ACK received, success
-                    return Ok(());
+                    return Ok(Reply::Ack(clock.now() - start_time));
                 } else if response == NACK_BYTE {
                     // NACK received, retry sending
+                    nack_received = true;
                     break;
+                } else if response == RESEND_BYTE && resend_width.is_some() {
+                    // Resend request, the sequence number follows
+                    resend_sequence = Some(Vec::new());
                 }
             }
         }
-        // Timeout, retry sending
+
+        // Timeout, back off before retrying unless this was the last attempt
+        if !nack_received && attempt + 1 < retries {
+            clock.sleep(remaining().map_or(timing.backoff, |left| left.min(timing.backoff)));
+        }
+    }
+
+    if remaining() == Some(Duration::ZERO) {
+        return Err(SimpError::DeadlineExceeded);
     }
-    Err("Failed to send packet after retries")
+    Err(SimpError::RetriesExhausted)
 }
 
 /// Function to receive a packet
-pub fn receive_packet(uart: &mut impl Uart) -> Result<super::packet::Packet, &'static str> {
+#[cfg(feature = "std")]
+pub fn receive_packet(uart: &mut impl Uart) -> Result<super::packet::Packet, SimpError> {
+    receive_packet_with_config(uart, &ProtocolConfig::default())
+}
+
+/// Function to receive a packet terminated by the configured end byte
+///
+/// Fails with `SimpError::FrameTooLarge` once more than `max_frame_size` bytes arrive
+/// without the end byte.
+#[cfg(feature = "std")]
+pub fn receive_packet_with_config(
+    uart: &mut impl Uart,
+    config: &ProtocolConfig,
+) -> Result<super::packet::Packet, SimpError> {
+    receive_packet_from_reader(&mut Halves(uart), config)
+}
+
+/// Function to receive a packet through the receiving half of a UART
+#[cfg(feature = "std")]
+pub fn receive_packet_from_reader(
+    reader: &mut impl Reader,
+    config: &ProtocolConfig,
+) -> Result<Packet, SimpError> {
     let mut buffer = Vec::new();
-    while let Some(byte) = uart.read() {
-        buffer.push(byte);
-        if byte == super::packet::END_BYTE {
-            return super::packet::Packet::from_bytes(&buffer);
+    while let Some(byte) = reader.try_read()? {
+        if push_frame_byte(&mut buffer, byte, config)? {
+            return Packet::from_bytes_with_config(&buffer, config);
+        }
+    }
+    Err(SimpError::Timeout)
+}
+
+/// Function to receive a packet, waiting up to `timeout` for it to arrive
+///
+/// Unlike `receive_packet`, a read that finds no data does not end the receive. Bytes
+/// are collected across such reads until the frame is complete or the timeout elapses,
+/// so a frame that trickles in from a real serial port is assembled whole.
+#[cfg(feature = "std")]
+pub fn receive_packet_with_timeout(
+    uart: &mut impl Uart,
+    timeout: Duration,
+) -> Result<Packet, SimpError> {
+    receive_packet_with_timeout_and_clock(
+        uart,
+        timeout,
+        &ProtocolConfig::default(),
+        &SystemClock::new(),
+    )
+}
+
+/// Function to receive a packet within a timeout, using the given protocol config and clock
+#[cfg(feature = "std")]
+pub fn receive_packet_with_timeout_and_clock(
+    uart: &mut impl Uart,
+    timeout: Duration,
+    config: &ProtocolConfig,
+    clock: &impl Clock,
+) -> Result<Packet, SimpError> {
+    let mut buffer = Vec::new();
+    let start_time = clock.now();
+    while clock.now() - start_time < timeout {
+        if let Some(byte) = uart.try_read()? {
+            if push_frame_byte(&mut buffer, byte, config)? {
+                return Packet::from_bytes_with_config(&buffer, config);
+            }
         }
     }
-    Err("Failed to receive packet")
+    Err(SimpError::Timeout)
+}
+
+/// Appends a received byte to the frame being assembled and reports whether it completes it.
+///
+/// Fails with `SimpError::FrameTooLarge` if the frame would grow past `max_frame_size`
+/// or no longer fits in the buffer.
+#[cfg(any(feature = "std", feature = "heapless"))]
+pub(crate) fn push_frame_byte(
+    buffer: &mut impl ReassemblyBuffer,
+    byte: u8,
+    config: &ProtocolConfig,
+) -> Result<bool, SimpError> {
+    if config.max_frame_size.is_some_and(|max| buffer.len() >= max) {
+        return Err(SimpError::FrameTooLarge);
+    }
+    buffer.push(&[byte]).map_err(|_| SimpError::FrameTooLarge)?;
+    Ok(if config.framing.is_escaped() {
+        byte == config.end_byte
+    } else {
+        frame_len(buffer.as_slice(), config) == Some(buffer.len())
+    })
+}
+
+/// Function to receive a packet and reply with an ACK or NACK
+///
+/// Returns the outcome of the receive together with the outcome of the reply. The reply is
+/// Ok(Some(ACK_BYTE)) for a valid packet, Ok(Some(NACK_BYTE)) for a frame that arrived
+/// damaged, Ok(None) when no reply was called for, or Err(ReplyFailed) when the reply could
+/// not be written. A failed reply never hides the received packet or the receive error.
+/// Only a frame that fails its checksum, length or structure check is NACKed, so the
+/// sender's retry fires right away. Other errors, such as no data or a device error, are
+/// not the sender's fault and get no reply.
+#[cfg(feature = "std")]
+pub fn receive_packet_with_ack(
+    uart: &mut impl Uart,
+) -> (Result<Packet, SimpError>, Result<Option<u8>, SimpError>) {
+    receive_packet_with_ack_and_config(uart, &ProtocolConfig::default())
+}
+
+/// Function to receive a packet using the given protocol config and reply with an ACK or NACK
+#[cfg(feature = "std")]
+pub fn receive_packet_with_ack_and_config(
+    uart: &mut impl Uart,
+    config: &ProtocolConfig,
+) -> (Result<Packet, SimpError>, Result<Option<u8>, SimpError>) {
+    let result = receive_packet_with_config(uart, config);
+    let reply = match &result {
+        Ok(_) => ACK_BYTE,
+        Err(error) if is_corrupt_frame(error) => NACK_BYTE,
+        Err(_) => return (result, Ok(None)),
+    };
+    match uart.write(&[reply]) {
+        Ok(1) => (result, Ok(Some(reply))),
+        _ => (result, Err(SimpError::ReplyFailed)),
+    }
+}
+
+/// Returns whether a receive failed because the frame arrived damaged, which calls for a NACK.
+#[cfg(feature = "std")]
+fn is_corrupt_frame(error: &SimpError) -> bool {
+    matches!(
+        error,
+        SimpError::ChecksumMismatch | SimpError::InvalidStructure | SimpError::LengthMismatch(_)
+    )
+}
+
+/// Function to receive a packet sent with `send_packet_with_sequenced_ack` and acknowledge it
+///
+/// Replies with ACK_BYTE followed by the packet's sequence number, or NACK_BYTE if the
+/// frame fails to decode. Returns the sequence number and the payload without it.
+#[cfg(feature = "std")]
+pub fn receive_packet_with_sequenced_ack(
+    uart: &mut impl Uart,
+    config: &ProtocolConfig,
+) -> Result<(usize, Vec<u8>), SimpError> {
+    let mut packet = match receive_packet_with_config(uart, config) {
+        Ok(packet) => packet,
+        Err(error) if is_corrupt_frame(&error) => {
+            uart.write(&[NACK_BYTE])?;
+            return Err(error);
+        }
+        Err(error) => return Err(error),
+    };
+    let width = config.sequence_width.bytes();
+    if packet.payload.len() < width {
+        return Err(SimpError::EmptyPacket);
+    }
+    let payload = packet.payload.split_off(width);
+    let mut reply = vec![ACK_BYTE];
+    reply.extend_from_slice(&packet.payload);
+    uart.write(&reply)?;
+    Ok((SequenceWidth::decode(&packet.payload), payload))
+}
+
+/// Function to receive a packet, using its length byte to find where it ends
+///
+/// Once the length byte has arrived, exactly that many payload bytes are read, followed
+/// by the checksum and end bytes. Unlike `receive_packet`, nothing past the frame is read
+/// even if its end byte was lost or corrupted on the line.
+#[cfg(feature = "std")]
+pub fn receive_packet_by_length(uart: &mut impl Uart) -> Result<Packet, SimpError> {
+    receive_packet_by_length_with_config(uart, &ProtocolConfig::default())
+}
+
+/// Function to receive a packet using its length byte and the given protocol config
+#[cfg(feature = "std")]
+pub fn receive_packet_by_length_with_config(
+    uart: &mut impl Uart,
+    config: &ProtocolConfig,
+) -> Result<Packet, SimpError> {
+    let mut buffer = Vec::new();
+    // Read the header until the length is known, then the rest of the frame
+    while Packet::frame_len(&buffer, config).is_none_or(|len| buffer.len() < len) {
+        buffer.push(uart.try_read()?.ok_or(SimpError::Timeout)?);
+    }
+    Packet::from_bytes_with_config(&buffer, config)
+}
+
+/// Error returned when a multi-packet transfer fails part way through
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialTransfer {
+    /// Number of packets acknowledged before the failure
+    pub delivered: usize,
+    /// Reason the transfer failed
+    pub error: SimpError,
 }
 
 /// Function to send multiple packets
+#[cfg(feature = "std")]
 pub fn send_multiple_packets_with_ack(
     uart: &mut impl Uart,
     data: &[u8],
     retries: usize,
     timeout: Duration,
-) -> Result<(), &'static str> {
-    let max_payload_size = 250; // Max size for the payload part of the packet
-    let mut sequence = 0u8;
+) -> Result<(), SimpError> {
+    send_multiple_packets_with_config(uart, data, retries, timeout, &ProtocolConfig::default())
+}
+
+/// Function to send multiple packets using the given protocol config
+#[cfg(feature = "std")]
+pub fn send_multiple_packets_with_config(
+    uart: &mut impl Uart,
+    data: &[u8],
+    retries: usize,
+    timeout: Duration,
+    config: &ProtocolConfig,
+) -> Result<(), SimpError> {
+    resume_multiple_packets_with_ack(uart, data, 0, retries, timeout, config).map_err(|e| e.error)
+}
+
+/// Function to resume sending multiple packets, starting at the given packet index
+///
+/// On failure the returned `PartialTransfer` reports how many packets were delivered,
+/// so the transfer can be resumed from there instead of restarting from packet 0.
+/// Use the offset reported by the receiver when it is available, since an ACK lost
+/// on the way back means the receiver may be one packet ahead of the sender.
+#[cfg(feature = "std")]
+pub fn resume_multiple_packets_with_ack(
+    uart: &mut impl Uart,
+    data: &[u8],
+    start_packet: usize,
+    retries: usize,
+    timeout: Duration,
+    config: &ProtocolConfig,
+) -> Result<(), PartialTransfer> {
+    let clock = SystemClock::new();
+    resume_multiple_packets_with_clock(uart, data, start_packet, retries, timeout, config, &clock)
+}
+
+/// Function to resume sending multiple packets, measuring time with the given clock
+///
+/// Behaves like `resume_multiple_packets_with_ack`. Passing a `MockClock` makes the ACK
+/// timeouts and the inter-packet delay deterministic in tests.
+#[cfg(feature = "std")]
+pub fn resume_multiple_packets_with_clock(
+    uart: &mut impl Uart,
+    data: &[u8],
+    start_packet: usize,
+    retries: usize,
+    timeout: Duration,
+    config: &ProtocolConfig,
+    clock: &impl Clock,
+) -> Result<(), PartialTransfer> {
+    let chunk_size = chunk_size(config).map_err(|error| PartialTransfer {
+        delivered: start_packet,
+        error,
+    })?;
+    let mut chunks: Vec<&[u8]> = data.chunks(chunk_size).collect();
+    // The receiver stops at the first short packet. Data that fills its last packet is
+    // followed by one that carries only the sequence number, so the end is never missed.
+    if data.len().is_multiple_of(chunk_size) {
+        chunks.push(&[]);
+    }
 
-    for chunk in data.chunks(max_payload_size) {
-        // Each chunk gets a sequence byte, which counts toward the payload size limit
-        let mut packet_data = vec![sequence];
-        packet_data.extend_from_slice(chunk);
-        let packet = Packet::new(packet_data);
+    let timing = AckTiming {
+        timeout,
+        backoff: Duration::ZERO,
+        deadline: None,
+    };
+
+    if config.transfer_start && start_packet == 0 {
+        let marker = Packet::new_with_config(TRANSFER_START.to_vec(), config);
+        send_packet_and_wait(uart, &marker, retries, &timing, clock, None, None).map_err(
+            |error| PartialTransfer {
+                delivered: 0,
+                error,
+            },
+        )?;
+    }
 
-        // Send packet and expect an ACK
-        send_packet_with_ack(uart, &packet, retries, timeout)?;
+    let mut index = start_packet;
+    while index < chunks.len() {
+        // Each chunk gets a sequence number, which counts toward the payload size limit.
+        // The sequence number wraps on overflow.
+        let mut packet_data = config.sequence_width.encode(index);
+        packet_data.extend_from_slice(chunks[index]);
+        let packet = Packet::new_with_config(packet_data, config);
+
+        // Send packet and expect an ACK or a request to resend from an earlier packet
+        let reply = send_packet_and_wait(
+            uart,
+            &packet,
+            retries,
+            &timing,
+            clock,
+            Some(config.sequence_width),
+            None,
+        )
+        .map_err(|error| PartialTransfer {
+            delivered: index,
+            error,
+        })?;
+
+        index = match reply {
+            Reply::Ack(_) => index + 1,
+            Reply::Resend(sequence) => {
+                // Rewind to the most recent packet carrying the requested sequence number
+                let distance = config.sequence_width.distance(sequence, index);
+                index.checked_sub(distance).ok_or(PartialTransfer {
+                    delivered: index,
+                    error: SimpError::InvalidResendRequest,
+                })?
+            }
+        };
 
-        // Increment sequence number, wrapping on overflow
-        sequence = sequence.wrapping_add(1);
+        if index < chunks.len() && !config.inter_packet_delay.is_zero() {
+            clock.sleep(config.inter_packet_delay);
+        }
     }
 
     Ok(())
 }
 
+/// Function to ask the sender of a multi-packet transfer to resend from the given packet
+///
+/// This is a NACK that carries the index of the packet the receiver expects next, so the
+/// sender rewinds to it instead of resending only the packet it is waiting on.
+#[cfg(feature = "std")]
+pub fn request_resend(
+    uart: &mut impl Uart,
+    packet_index: usize,
+    config: &ProtocolConfig,
+) -> Result<usize, SimpError> {
+    let mut request = vec![RESEND_BYTE];
+    request.extend(config.sequence_width.encode(packet_index));
+    uart.write(&request)
+}
+
+/// Function to send a keepalive frame during a multi-packet transfer
+///
+/// A keepalive is a packet with an empty payload. It proves the link is alive while the
+/// sender is slow to produce the next chunk, and the multi-packet receive functions skip
+/// it without counting it as a packet. It is not acknowledged. The frame uses the
+/// start and end bytes, checksum and framing of the given config, like the transfer's
+/// data packets.
+#[cfg(feature = "std")]
+pub fn send_keepalive(uart: &mut impl Uart, config: &ProtocolConfig) -> Result<usize, SimpError> {
+    send_packet(uart, &Packet::new_with_config(Vec::new(), config))
+}
+
+/// Function to compute how many packets a multi-packet transfer of `data_len` bytes requires
+///
+/// Every packet spends `config.sequence_width` bytes of `config.max_payload_size` on its
+/// sequence number, and carries the rest as data. The last packet is always short, so data
+/// that fills its packets exactly takes one more packet with no data to end the transfer.
+/// The transfer-start marker counts as a packet when `config.transfer_start` is set.
+///
+/// Fails with `SimpError::InvalidConfig` if the max payload size leaves no room for data.
+#[cfg(feature = "std")]
+pub fn packet_count(data_len: usize, config: &ProtocolConfig) -> Result<usize, SimpError> {
+    let marker = usize::from(config.transfer_start);
+    Ok(data_len / chunk_size(config)? + 1 + marker)
+}
+
+/// Returns how many bytes of data fit in each packet of a multi-packet transfer.
+#[cfg(feature = "std")]
+fn chunk_size(config: &ProtocolConfig) -> Result<usize, SimpError> {
+    config
+        .max_payload_size
+        .checked_sub(config.sequence_width.bytes())
+        .filter(|&size| size > 0)
+        .ok_or(SimpError::InvalidConfig)
+}
+
 /// Function to receive multiple packets
-pub fn receive_multiple_packets(uart: &mut impl Uart) -> Result<Vec<u8>, &'static str> {
+#[cfg(feature = "std")]
+pub fn receive_multiple_packets(uart: &mut impl Uart) -> Result<Vec<u8>, SimpError> {
+    receive_multiple_packets_with_config(uart, &ProtocolConfig::default())
+}
+
+/// Function to receive multiple packets using the given protocol config
+#[cfg(feature = "std")]
+pub fn receive_multiple_packets_with_config(
+    uart: &mut impl Uart,
+    config: &ProtocolConfig,
+) -> Result<Vec<u8>, SimpError> {
     let mut data = Vec::new();
-    let mut expected_sequence = 0u8;
+    let mut received_packets = 0;
+    resume_receive_multiple_packets(uart, &mut data, &mut received_packets, config)?;
+    Ok(data)
+}
+
+/// Reason a multi-packet receive stopped
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    /// The sender finished the transfer with a packet shorter than the maximum payload size
+    ShortPacket,
+    /// The configured `max_packets` limit was reached, so the data may be truncated
+    PacketLimit,
+}
+
+/// Function to receive multiple packets and report why the receive stopped
+///
+/// Unlike `receive_multiple_packets_with_config`, reaching the `max_packets` limit is not
+/// an error: the data received so far is returned with `Termination::PacketLimit`.
+#[cfg(feature = "std")]
+pub fn receive_multiple_packets_with_termination(
+    uart: &mut impl Uart,
+    config: &ProtocolConfig,
+) -> Result<(Vec<u8>, Termination), SimpError> {
+    let mut data = Vec::new();
+    let mut received_packets = 0;
+    let termination = receive_chunks(uart, &mut received_packets, config, |chunk| {
+        data.extend_from_slice(chunk);
+        Ok(())
+    })?;
+    Ok((data, termination))
+}
+
+/// Function to receive multiple packets along with the CRC-32 of the reassembled data
+///
+/// The hash is updated as each chunk arrives. CLI tools can print it so the user can
+/// compare it with the hash of the source, such as the output of `crc32` on the file.
+#[cfg(feature = "std")]
+pub fn receive_multiple_packets_with_crc32(
+    uart: &mut impl Uart,
+    config: &ProtocolConfig,
+) -> Result<(Vec<u8>, u32), SimpError> {
+    let mut data = Vec::new();
+    let mut crc = 0;
+    let mut received_packets = 0;
+    receive_chunks(uart, &mut received_packets, config, |chunk| {
+        data.extend_from_slice(chunk);
+        crc = Packet::update_crc32(crc, chunk);
+        Ok(())
+    })
+    .and_then(complete_transfer)?;
+    Ok((data, crc))
+}
+
+/// Function to receive multiple packets into the given reassembly buffer
+///
+/// With a fixed-capacity buffer such as `SliceBuffer`, the receive fails with
+/// `SimpError::BufferFull` once the data no longer fits.
+#[cfg(feature = "std")]
+pub fn receive_multiple_packets_into(
+    uart: &mut impl Uart,
+    buffer: &mut impl ReassemblyBuffer,
+    config: &ProtocolConfig,
+) -> Result<(), SimpError> {
+    let mut received_packets = 0;
+    resume_receive_multiple_packets(uart, buffer, &mut received_packets, config)
+}
+
+/// Function to receive multiple packets into an array on the stack
+///
+/// Returns the array and the number of bytes filled, so the reassembled data is never
+/// reallocated as it grows. Each frame is still decoded into a heap-allocated `Packet`;
+/// without an allocator, receive frames with `frame::receive_frame` instead. Fails with
+/// `SimpError::BufferFull` if the transfer is longer than `N` bytes.
+#[cfg(feature = "std")]
+pub fn receive_multiple_packets_array<const N: usize>(
+    uart: &mut impl Uart,
+) -> Result<([u8; N], usize), SimpError> {
+    receive_multiple_packets_array_with_config(uart, &ProtocolConfig::default())
+}
+
+/// Function to receive multiple packets into an array using the given protocol config
+#[cfg(feature = "std")]
+pub fn receive_multiple_packets_array_with_config<const N: usize>(
+    uart: &mut impl Uart,
+    config: &ProtocolConfig,
+) -> Result<([u8; N], usize), SimpError> {
+    let mut storage = [0u8; N];
+    let mut buffer = SliceBuffer::new(&mut storage);
+    receive_multiple_packets_into(uart, &mut buffer, config)?;
+    let len = buffer.len();
+    Ok((storage, len))
+}
+
+/// Function to resume receiving multiple packets
+///
+/// Received data is appended to `data` and `received_packets` is advanced as packets
+/// arrive, so after a failure both hold the progress made so far. The caller can report
+/// `received_packets` to the sender as the offset to resume from and call this again.
+#[cfg(feature = "std")]
+pub fn resume_receive_multiple_packets(
+    uart: &mut impl Uart,
+    data: &mut impl ReassemblyBuffer,
+    received_packets: &mut usize,
+    config: &ProtocolConfig,
+) -> Result<(), SimpError> {
+    receive_chunks(uart, received_packets, config, |chunk| data.push(chunk))
+        .and_then(complete_transfer)
+}
+
+/// Function to receive multiple packets, writing each chunk to `writer` as it arrives
+///
+/// Unlike `receive_multiple_packets` the data is never held in memory as a whole, which
+/// keeps memory use bounded for very large transfers. Returns the number of bytes written.
+#[cfg(feature = "std")]
+pub fn receive_multiple_packets_to_writer<W: Write>(
+    uart: &mut impl Uart,
+    writer: &mut W,
+) -> Result<usize, SimpError> {
+    let mut received_packets = 0;
+    let mut written = 0;
+    receive_chunks(
+        uart,
+        &mut received_packets,
+        &ProtocolConfig::default(),
+        |chunk| {
+            writer
+                .write_all(chunk)
+                .map_err(|_| SimpError::OutputFailed)?;
+            written += chunk.len();
+            Ok(())
+        },
+    )
+    .and_then(complete_transfer)?;
+    Ok(written)
+}
+
+/// Turns a receive that stopped before the sender finished into an error
+#[cfg(feature = "std")]
+pub(crate) fn complete_transfer(termination: Termination) -> Result<(), SimpError> {
+    match termination {
+        Termination::ShortPacket => Ok(()),
+        Termination::PacketLimit => Err(SimpError::TooManyPackets),
+    }
+}
+
+/// Receives the packets of a multi-packet transfer, passing each chunk to `on_chunk`
+#[cfg(feature = "std")]
+fn receive_chunks(
+    uart: &mut impl Uart,
+    received_packets: &mut usize,
+    config: &ProtocolConfig,
+    mut on_chunk: impl FnMut(&[u8]) -> Result<(), SimpError>,
+) -> Result<Termination, SimpError> {
+    // A resumed transfer has already seen its marker
+    let mut started = !config.transfer_start || *received_packets > 0;
 
     loop {
-        let packet = receive_packet(uart)?;
-        if packet.payload.is_empty() {
-            return Err("Empty packet received");
+        if config
+            .max_packets
+            .is_some_and(|max| *received_packets >= max)
+        {
+            return Ok(Termination::PacketLimit);
         }
 
-        let sequence = packet.payload[0];
-        if sequence != expected_sequence {
-            return Err("Packet sequence out of order");
+        let packet = receive_packet_with_config(uart, config)?;
+        match classify_packet(&packet, *received_packets, &mut started, config)? {
+            PacketKind::Skipped => continue,
+            PacketKind::Chunk { data, last } => {
+                on_chunk(data)?;
+                *received_packets += 1;
+                if last {
+                    return Ok(Termination::ShortPacket);
+                }
+            }
         }
+    }
+}
 
-        data.extend_from_slice(&packet.payload[1..]);
-        expected_sequence = expected_sequence.wrapping_add(1);
+/// Role of a received packet within a multi-packet transfer
+#[cfg(feature = "std")]
+pub(crate) enum PacketKind<'a> {
+    /// A keepalive or a frame received before the transfer started
+    Skipped,
+    /// The next chunk of data, and whether it is the final one
+    Chunk { data: &'a [u8], last: bool },
+}
 
-        if packet.payload.len() < 250 {
-            // If the last packet's payload is less than max, it is the final packet
-            break;
-        }
+/// Checks a received packet against the expected sequence number and extracts its chunk
+///
+/// The sequence number travels inside the payload, so it is covered by the checksum and a
+/// corrupted one is reported as a checksum mismatch rather than as a reordered packet.
+#[cfg(feature = "std")]
+pub(crate) fn classify_packet<'a>(
+    packet: &'a Packet,
+    received_packets: usize,
+    started: &mut bool,
+    config: &ProtocolConfig,
+) -> Result<PacketKind<'a>, SimpError> {
+    if !*started {
+        // Discard leftovers of an earlier transfer until the marker arrives
+        *started = packet.payload == TRANSFER_START;
+        return Ok(PacketKind::Skipped);
+    }
+    if packet.payload.is_empty() {
+        // Keepalive, the sender is still there
+        return Ok(PacketKind::Skipped);
+    }
+    let sequence_width = config.sequence_width.bytes();
+    if packet.payload.len() < sequence_width {
+        return Err(SimpError::EmptyPacket);
     }
 
-    Ok(data)
+    let (sequence, data) = packet.payload.split_at(sequence_width);
+    if sequence != config.sequence_width.encode(received_packets) {
+        return Err(SimpError::SequenceOutOfOrder);
+    }
+
+    Ok(PacketKind::Chunk {
+        data,
+        // If the last packet's payload is less than max, it is the final packet
+        last: packet.payload.len() < config.max_payload_size,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::cell::RefCell;
+    use crate::clock::MockClock;
+    use crate::mocks::{
+        multi_packet_frames, multi_packet_stream, roundtrip, roundtrip_with_config, MockUart,
+    };
+    use crate::packet::Framing;
+    use std::collections::VecDeque;
+    use std::time::Instant;
+
+    #[test]
+    fn test_send_packet() {
+        let mut uart = MockUart::new();
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+
+        let result = send_packet(&mut uart, &packet);
+        assert!(result.is_ok());
+
+        // Verify that the correct data was "sent"
+        let expected_data = packet.to_bytes();
+        assert_eq!(uart.get_written_data(), expected_data);
+    }
+
+    #[test]
+    fn test_send_packet_write_stalled() {
+        let mut uart = MockUart::new();
+        uart.stall_writes();
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
 
-    struct MockUart {
-        // This will hold the data that the mock UART "sends" or "receives"
-        write_data: RefCell<Vec<u8>>,
-        read_data: RefCell<Vec<u8>>,
+        let result = send_packet(&mut uart, &packet);
+        assert_eq!(result.err().unwrap(), SimpError::WriteStalled);
+
+        // A stalled write also aborts the ACK retries
+        let result = send_packet_with_ack(&mut uart, &packet, 3, Duration::from_millis(50));
+        assert_eq!(result.err().unwrap(), SimpError::WriteStalled);
+        assert!(uart.get_written_data().is_empty());
     }
 
-    impl MockUart {
-        fn new() -> Self {
-            MockUart {
-                write_data: RefCell::new(Vec::new()),
-                read_data: RefCell::new(Vec::new()),
-            }
-        }
+    #[test]
+    fn test_send_packet_with_ack_success() {
+        let mut uart = MockUart::new();
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
 
-        fn set_read_data(&self, data: Vec<u8>) {
-            *self.read_data.borrow_mut() = data;
-        }
+        // Set the mock to return an ACK after the packet is sent
+        uart.set_read_data(vec![ACK_BYTE]);
 
-        fn get_written_data(&self) -> Vec<u8> {
-            self.write_data.borrow().clone()
-        }
+        let result = send_packet_with_ack(&mut uart, &packet, 3, Duration::from_millis(500));
+        assert!(result.is_ok());
+
+        // Verify that the correct data was "sent"
+        let expected_data = packet.to_bytes();
+        assert_eq!(uart.get_written_data(), expected_data);
     }
 
-    impl Uart for MockUart {
-        fn write(&mut self, data: &[u8]) -> Result<usize, &'static str> {
-            self.write_data.borrow_mut().extend_from_slice(data);
-            Ok(data.len())
+    #[test]
+    fn test_send_packet_with_ack_failure() {
+        let mut uart = MockUart::new();
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+
+        // Set the mock to return nothing (no ACK or NACK)
+        uart.set_read_data(vec![]);
+
+        let result = send_packet_with_ack(&mut uart, &packet, 3, Duration::from_millis(500));
+        assert!(result.is_err());
+        assert_eq!(result.err().unwrap(), SimpError::RetriesExhausted);
+
+        // Verify that the packet was sent 3 times due to retries
+        let expected_data = packet.to_bytes();
+        let expected_sent_data = expected_data.repeat(3);
+        assert_eq!(uart.get_written_data(), expected_sent_data);
+    }
+
+    // Receiver that ACKs every packet written to it
+    struct AckingUart {
+        inner: MockUart<'static>,
+    }
+
+    impl Uart for AckingUart {
+        fn write(&mut self, data: &[u8]) -> Result<usize, SimpError> {
+            let written = self.inner.write(data)?;
+            self.inner.set_read_data(vec![ACK_BYTE]);
+            Ok(written)
         }
 
         fn read(&mut self) -> Option<u8> {
-            if self.read_data.borrow().is_empty() {
-                None
-            } else {
-                Some(self.read_data.borrow_mut().remove(0))
-            }
+            self.inner.read()
         }
     }
 
-    #[cfg(test)]
-    mod tests {
-        use super::*;
-        use std::cell::RefCell;
+    #[test]
+    fn test_send_packet_with_ack_recovers_from_dropped_acks() {
+        let mut uart = AckingUart {
+            inner: MockUart::new(),
+        };
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
 
-        struct MockUart {
-            // This will hold the data that the mock UART "sends" or "receives"
-            write_data: RefCell<Vec<u8>>,
-            read_data: RefCell<Vec<u8>>,
-        }
+        // The ACKs to the first two attempts are lost on the line
+        uart.inner.lose_next_bytes(2);
 
-        impl MockUart {
-            fn new() -> Self {
-                MockUart {
-                    write_data: RefCell::new(Vec::new()),
-                    read_data: RefCell::new(Vec::new()),
-                }
-            }
+        let result = send_packet_with_ack(&mut uart, &packet, 3, Duration::from_millis(20));
+        assert_eq!(result, Ok(()));
+        assert_eq!(uart.inner.get_written_data(), packet.to_bytes().repeat(3));
+    }
 
-            fn set_read_data(&self, data: Vec<u8>) {
-                *self.read_data.borrow_mut() = data;
-            }
+    #[test]
+    fn test_request_response_over_pair() {
+        let (mut client, mut server) = MockUart::pair();
+
+        send_packet(&mut client, &Packet::new(b"PING 7".to_vec())).unwrap();
+        let (request, _) = receive_packet_with_ack(&mut server);
+        let request = request.unwrap();
+        assert_eq!(request.payload, b"PING 7");
+        // The ACK went back to the client
+        assert_eq!(client.read(), Some(ACK_BYTE));
+
+        let mut response = b"PONG ".to_vec();
+        response.extend_from_slice(&request.payload[5..]);
+        send_packet(&mut server, &Packet::new(response)).unwrap();
+        assert_eq!(
+            receive_packet_with_ack(&mut client).0.unwrap().payload,
+            b"PONG 7"
+        );
+        assert_eq!(server.read(), Some(ACK_BYTE));
+        assert_eq!(client.read(), None);
+    }
 
-            fn get_written_data(&self) -> Vec<u8> {
-                self.write_data.borrow().clone()
-            }
+    #[test]
+    fn test_send_packet_with_ack_write_failure() {
+        let mut uart = MockUart::new();
+        uart.set_read_data(vec![ACK_BYTE]);
+        uart.fail_next_write(1);
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+
+        // A failed write is a device error, so it is not retried
+        let result = send_packet_with_ack(&mut uart, &packet, 3, Duration::from_millis(20));
+        assert_eq!(
+            result,
+            Err(SimpError::Uart("Failed to write to serial port"))
+        );
+        assert!(uart.get_written_data().is_empty());
+
+        // The next write goes through
+        let result = send_packet_with_ack(&mut uart, &packet, 3, Duration::from_millis(20));
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn test_receive_packet_corrupted_on_the_line() {
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+        let mut uart = MockUart::new();
+        uart.set_read_data(packet.to_bytes());
+        uart.corrupt_byte_at(3);
+        assert_eq!(
+            receive_packet(&mut uart).err(),
+            Some(SimpError::ChecksumMismatch)
+        );
+
+        // Only one byte is corrupted
+        uart.set_read_data(packet.to_bytes());
+        assert_eq!(receive_packet(&mut uart).unwrap().payload, packet.payload);
+    }
+
+    #[test]
+    fn test_receive_packet_success() {
+        assert_eq!(
+            roundtrip(&[0x01, 0x02, 0x03]).unwrap(),
+            vec![0x01, 0x02, 0x03]
+        );
+    }
+
+    #[test]
+    fn test_receive_packet_failure() {
+        let mut uart = MockUart::new();
+
+        // Set the mock to provide an incomplete packet
+        uart.set_read_data(vec![crate::packet::START_BYTE, 0x03, 0x01, 0x02]);
+
+        let result = receive_packet(&mut uart);
+        assert!(result.is_err());
+        assert_eq!(result.err().unwrap(), SimpError::Timeout);
+    }
+
+    #[test]
+    fn test_send_multiple_packets_with_ack() {
+        let mut uart = MockUart::new();
+        let data = vec![0x02; 800]; // Data larger than 256 bytes
+
+        // Simulate an ACK for each packet sent
+        uart.set_read_data(vec![ACK_BYTE; 4]);
+
+        let result =
+            send_multiple_packets_with_ack(&mut uart, &data, 3, Duration::from_millis(500));
+        assert!(result.is_ok());
+
+        // Extract sent data for debugging
+        let sent_data = uart.get_written_data();
+        println!("Sent Data: {:?}", sent_data);
+
+        // Define the expected number of packets (800 bytes, max 250 payload per packet, so 4 packets)
+        let max_payload_size = 250;
+        let mut expected_sequence = 0u8;
+
+        // Iterate over chunks of sent data, assuming each packet is prefixed with START_BYTE and ends with END_BYTE
+        let mut offset = 0;
+        while offset < sent_data.len() {
+            assert_eq!(sent_data[offset], crate::packet::START_BYTE); // Check start byte
+            offset += 1;
+
+            let length = sent_data[offset] as usize; // Get the packet length
+            offset += 1;
+
+            assert_eq!(sent_data[offset], expected_sequence); // Check sequence number
+            offset += 1;
+
+            // Calculate expected payload length
+            let payload_length = length - 1; // Length includes sequence byte but not checksum
+
+            // Verify payload bytes
+            let payload_end = offset + payload_length;
+            assert!(payload_end < sent_data.len());
+
+            let payload = &sent_data[offset..payload_end];
+            let expected_payload_start = (expected_sequence as usize) * (max_payload_size - 1);
+            let expected_payload_end = expected_payload_start + payload.len();
+            let expected_payload = &data[expected_payload_start..expected_payload_end];
+            assert_eq!(payload, expected_payload);
+
+            offset = payload_end;
+
+            // Verify checksum
+            let checksum_start = offset - payload_length - 1; // sequence byte + payload
+            let checksum_data = &sent_data[checksum_start..payload_end];
+            let calculated_checksum = Packet::calculate_checksum(checksum_data);
+            let actual_checksum = sent_data[offset];
+            assert_eq!(actual_checksum, calculated_checksum);
+            offset += 1;
+
+            assert_eq!(sent_data[offset], crate::packet::END_BYTE); // Check end byte
+            offset += 1;
+
+            // Increment sequence number, wrapping on overflow
+            expected_sequence = expected_sequence.wrapping_add(1);
         }
 
-        impl Uart for MockUart {
-            fn write(&mut self, data: &[u8]) -> Result<usize, &'static str> {
-                self.write_data.borrow_mut().extend_from_slice(data);
-                Ok(data.len())
-            }
+        // Ensure we processed the correct number of packets
+        assert_eq!(expected_sequence, 4); // Should have sent 4 packets
+    }
 
-            fn read(&mut self) -> Option<u8> {
-                if self.read_data.borrow().is_empty() {
-                    None
-                } else {
-                    Some(self.read_data.borrow_mut().remove(0))
-                }
-            }
+    #[test]
+    fn test_receive_multiple_packets() {
+        let mut uart = MockUart::new();
+        let data = vec![0x01; 600]; // Data larger than 256 bytes
+
+        // Create packets with sequence numbers and set to mock UART
+        let mut packet_data = Vec::new();
+        let mut sequence = 0u8;
+        for chunk in data.chunks(250) {
+            let mut chunk_with_seq = vec![sequence];
+            chunk_with_seq.extend_from_slice(chunk);
+            let packet = Packet::new(chunk_with_seq);
+            packet_data.extend(packet.to_bytes());
+            sequence = sequence.wrapping_add(1);
         }
+        uart.set_read_data(packet_data);
 
-        #[test]
-        fn test_send_packet() {
-            let mut uart = MockUart::new();
-            let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+        let result = receive_multiple_packets(&mut uart);
+        assert!(result.is_ok());
 
-            let result = send_packet(&mut uart, &packet);
-            assert!(result.is_ok());
+        let received_data = result.unwrap();
+        assert_eq!(received_data, data);
+    }
 
-            // Verify that the correct data was "sent"
-            let expected_data = packet.to_bytes();
-            assert_eq!(uart.get_written_data(), expected_data);
-        }
+    #[test]
+    fn test_resume_multiple_packets_with_ack() {
+        let mut uart = MockUart::new();
+        let data: Vec<u8> = (0..900).map(|i| (i % 100) as u8).collect();
+
+        // Only the first two packets get acknowledged
+        uart.set_read_data(vec![ACK_BYTE; 2]);
+
+        let result = resume_multiple_packets_with_ack(
+            &mut uart,
+            &data,
+            0,
+            1,
+            Duration::from_millis(50),
+            &ProtocolConfig::default(),
+        );
+        let partial = result.err().unwrap();
+        assert_eq!(partial.delivered, 2);
+        assert_eq!(partial.error, SimpError::RetriesExhausted);
+
+        // Resume from the failed packet and acknowledge the rest
+        let mut uart = MockUart::new();
+        uart.set_read_data(vec![ACK_BYTE; 2]);
+
+        let result = resume_multiple_packets_with_ack(
+            &mut uart,
+            &data,
+            partial.delivered,
+            1,
+            Duration::from_millis(50),
+            &ProtocolConfig::default(),
+        );
+        assert!(result.is_ok());
+
+        // Only the remaining packets were sent, starting at sequence 2
+        let expected_data = multi_packet_frames(&data, &ProtocolConfig::default())[2..].concat();
+        assert_eq!(uart.get_written_data(), expected_data);
+    }
 
-        #[test]
-        fn test_send_packet_with_ack_success() {
-            let mut uart = MockUart::new();
-            let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+    #[test]
+    fn test_resume_receive_multiple_packets() {
+        let data: Vec<u8> = (0..900).map(|i| (i % 100) as u8).collect();
+        let packets = multi_packet_frames(&data, &ProtocolConfig::default());
+
+        // The link drops out after the first two packets
+        let mut uart = MockUart::new();
+        uart.set_read_data(packets[..2].concat());
+
+        let config = ProtocolConfig::default();
+        let mut received = Vec::new();
+        let mut received_packets = 0;
+        let result = resume_receive_multiple_packets(
+            &mut uart,
+            &mut received,
+            &mut received_packets,
+            &config,
+        );
+        assert!(result.is_err());
+        assert_eq!(received_packets, 2);
+
+        // The sender resumes from the offset reported by the receiver
+        uart.set_read_data(packets[received_packets..].concat());
+        let result = resume_receive_multiple_packets(
+            &mut uart,
+            &mut received,
+            &mut received_packets,
+            &config,
+        );
+        assert!(result.is_ok());
+        assert_eq!(received_packets, 4);
+        assert_eq!(received, data);
+    }
+
+    #[test]
+    fn test_receive_packet_by_length_matches_scan() {
+        let payloads = vec![
+            vec![0x01, 0x02, 0x03],
+            vec![crate::packet::START_BYTE, 0x01, crate::packet::END_BYTE],
+            vec![crate::packet::ESCAPE_BYTE; 10],
+            vec![0x55; 200],
+        ];
 
-            // Set the mock to return an ACK after the packet is sent
-            uart.set_read_data(vec![ACK_BYTE]);
+        let mut uart = MockUart::new();
+        for payload in payloads {
+            let packet = Packet::new(payload.clone());
 
-            let result = send_packet_with_ack(&mut uart, &packet, 3, Duration::from_millis(500));
-            assert!(result.is_ok());
+            uart.set_read_data(packet.to_bytes());
+            let scanned = receive_packet(&mut uart).unwrap();
+            uart.set_read_data(packet.to_bytes());
+            let by_length = receive_packet_by_length(&mut uart).unwrap();
 
-            // Verify that the correct data was "sent"
-            let expected_data = packet.to_bytes();
-            assert_eq!(uart.get_written_data(), expected_data);
+            assert_eq!(scanned.payload, payload);
+            assert_eq!(by_length.payload, scanned.payload);
+            assert_eq!(by_length.length, scanned.length);
+            assert_eq!(by_length.checksum, scanned.checksum);
         }
+    }
 
-        #[test]
-        fn test_send_packet_with_ack_failure() {
-            let mut uart = MockUart::new();
-            let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+    #[test]
+    fn test_receive_packet_by_length_checksum_equals_end_byte() {
+        let mut uart = MockUart::new();
+        let packet = Packet::new(vec![0x40, 0x3F]);
+        assert_eq!(packet.checksum, crate::packet::END_BYTE as u16);
+
+        // The checksum is escaped, so the scan no longer stops at it
+        let bytes = packet.to_bytes();
+        assert_eq!(
+            bytes[bytes.len() - 3..bytes.len() - 1],
+            [
+                crate::packet::ESCAPE_BYTE,
+                crate::packet::END_BYTE ^ crate::packet::ESCAPE_XOR
+            ]
+        );
+        uart.set_read_data(bytes.clone());
+        assert_eq!(receive_packet(&mut uart).unwrap().payload, vec![0x40, 0x3F]);
+
+        uart.set_read_data(bytes);
+        let received_packet = receive_packet_by_length(&mut uart).unwrap();
+        assert_eq!(received_packet.payload, vec![0x40, 0x3F]);
+    }
 
-            // Set the mock to return nothing (no ACK or NACK)
-            uart.set_read_data(vec![]);
+    #[test]
+    fn test_receive_packet_by_length_truncated() {
+        let mut uart = MockUart::new();
+        let bytes = Packet::new(vec![0x01, 0x02, 0x03]).to_bytes();
+        uart.set_read_data(bytes[..bytes.len() - 1].to_vec());
 
-            let result = send_packet_with_ack(&mut uart, &packet, 3, Duration::from_millis(500));
-            assert!(result.is_err());
-            assert_eq!(result.err().unwrap(), "Failed to send packet after retries");
+        let result = receive_packet_by_length(&mut uart);
+        assert_eq!(result.err().unwrap(), SimpError::Timeout);
+    }
 
-            // Verify that the packet was sent 3 times due to retries
-            let expected_data = packet.to_bytes();
-            let expected_sent_data = expected_data.repeat(3);
-            assert_eq!(uart.get_written_data(), expected_sent_data);
-        }
+    #[test]
+    fn test_send_and_receive_long_packet() {
+        let config = ProtocolConfig {
+            framing: Framing::EscapedLong,
+            ..Default::default()
+        };
+        let payload: Vec<u8> = (0..4096).map(|i| i as u8).collect();
+        let packet = Packet::new_with_config(payload.clone(), &config);
+
+        let mut uart = MockUart::new();
+        send_packet(&mut uart, &packet).unwrap();
+        let bytes = uart.get_written_data();
+        uart.set_read_data(bytes.clone());
+        assert_eq!(
+            receive_packet_by_length_with_config(&mut uart, &config)
+                .unwrap()
+                .payload,
+            payload
+        );
+
+        // The default framing cannot carry it
+        assert_eq!(
+            send_packet(&mut uart, &Packet::new(payload)).err(),
+            Some(SimpError::PayloadTooLarge)
+        );
+
+        uart.set_read_data(bytes[..bytes.len() / 2].to_vec());
+        assert_eq!(
+            receive_packet_by_length_with_config(&mut uart, &config).err(),
+            Some(SimpError::Timeout)
+        );
+    }
 
-        #[test]
-        fn test_receive_packet_success() {
-            let mut uart = MockUart::new();
-            let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+    #[test]
+    fn test_multiple_packets_length_prefixed() {
+        let config = ProtocolConfig {
+            framing: Framing::LengthPrefixed,
+            ..Default::default()
+        };
+        // Control bytes only, which would double in size with escaping
+        let data: Vec<u8> = [
+            crate::packet::START_BYTE,
+            crate::packet::END_BYTE,
+            crate::packet::ESCAPE_BYTE,
+        ]
+        .repeat(200);
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(vec![ACK_BYTE; 3]);
+        send_multiple_packets_with_config(&mut uart, &data, 1, Duration::from_millis(50), &config)
+            .unwrap();
+        let sent_data = uart.get_written_data();
+        // Three packets of sequence number and chunk, each with four bytes of framing
+        assert_eq!(sent_data.len(), data.len() + 3 * (1 + 4));
+
+        uart.set_read_data(sent_data);
+        let received_data = receive_multiple_packets_with_config(&mut uart, &config).unwrap();
+        assert_eq!(received_data, data);
+    }
 
-            // Set the mock to provide the bytes of a complete packet
-            uart.set_read_data(packet.to_bytes());
+    #[test]
+    fn test_receive_packet_with_custom_end_byte() {
+        let mut uart = MockUart::new();
+        let config = ProtocolConfig {
+            end_byte: 0x0A,
+            ..Default::default()
+        };
+        // The payload contains the end byte, which must not terminate the frame early
+        let payload = vec![0x01, 0x0A, crate::packet::END_BYTE, 0x02];
+        let packet = Packet::new_with_config(payload.clone(), &config);
+        assert_eq!(packet.to_bytes().last(), Some(&0x0A));
+        assert_eq!(packet.to_bytes().iter().filter(|&&b| b == 0x0A).count(), 1);
+        assert_eq!(roundtrip_with_config(&payload, &config).unwrap(), payload);
+
+        uart.set_read_data(packet.to_bytes());
+        let received_packet = receive_packet_with_config(&mut uart, &config).unwrap();
+        assert_eq!(received_packet.end_byte, 0x0A);
+    }
 
-            let result = receive_packet(&mut uart);
-            assert!(result.is_ok());
+    #[test]
+    fn test_multiple_packets_exact_multiple() {
+        // Two full chunks, which used to leave the receiver waiting for a short packet
+        let data: Vec<u8> = (0..498).map(|i| (i % 100) as u8).collect();
+        let mut uart = MockUart::new();
+        uart.set_read_data(vec![ACK_BYTE; 3]);
+        send_multiple_packets_with_ack(&mut uart, &data, 1, Duration::from_millis(50)).unwrap();
 
-            // Verify the received packet is as expected
-            let received_packet = result.unwrap();
-            assert_eq!(received_packet.payload, packet.payload);
-        }
+        let sent_data = uart.get_written_data();
+        // The closing packet carries only its sequence number
+        assert!(sent_data.ends_with(&Packet::new(vec![2]).to_bytes()));
 
-        #[test]
-        fn test_receive_packet_failure() {
-            let mut uart = MockUart::new();
+        uart.set_read_data(sent_data);
+        assert_eq!(receive_multiple_packets(&mut uart).unwrap(), data);
+    }
+
+    #[test]
+    fn test_multiple_packets_small_max_payload() {
+        let config = ProtocolConfig {
+            max_payload_size: 16,
+            ..Default::default()
+        };
+        let data: Vec<u8> = (0..100).collect();
+        let expected_packets = packet_count(data.len(), &config).unwrap();
+        assert_eq!(expected_packets, 7);
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(vec![ACK_BYTE; expected_packets]);
+        send_multiple_packets_with_config(&mut uart, &data, 1, Duration::from_millis(50), &config)
+            .unwrap();
+        let sent_data = uart.get_written_data();
+        // Sequence number and 15 bytes of data in every full packet
+        assert!(sent_data.starts_with(&Packet::new([&[0], &data[..15]].concat()).to_bytes()));
+
+        uart.set_read_data(sent_data);
+        assert_eq!(
+            receive_multiple_packets_with_config(&mut uart, &config).unwrap(),
+            data
+        );
+
+        // A receiver with the default size would stop after the first packet, which the
+        // config handshake catches before any data is sent
+        assert_ne!(
+            config.fingerprint(),
+            ProtocolConfig::default().fingerprint()
+        );
+    }
+
+    #[test]
+    fn test_send_multiple_packets_max_payload_without_room() {
+        let config = ProtocolConfig {
+            max_payload_size: 1,
+            ..Default::default()
+        };
+        let mut uart = MockUart::new();
+        let result =
+            send_multiple_packets_with_config(&mut uart, &[0x01], 1, Duration::ZERO, &config);
+        assert_eq!(result.err(), Some(SimpError::InvalidConfig));
+        assert!(uart.get_written_data().is_empty());
+    }
 
-            // Set the mock to provide an incomplete packet
-            uart.set_read_data(vec![crate::packet::START_BYTE, 0x03, 0x01, 0x02]);
+    #[test]
+    fn test_packet_count() {
+        let config = ProtocolConfig::default();
+        assert_eq!(packet_count(0, &config), Ok(1));
+        assert_eq!(packet_count(1, &config), Ok(1));
+        assert_eq!(packet_count(248, &config), Ok(1));
+        assert_eq!(packet_count(249, &config), Ok(2));
+        assert_eq!(packet_count(250, &config), Ok(2));
+        assert_eq!(packet_count(498, &config), Ok(3));
+        assert_eq!(packet_count(499, &config), Ok(3));
+
+        let small = |max_payload_size| ProtocolConfig {
+            max_payload_size,
+            ..Default::default()
+        };
+        assert_eq!(packet_count(100, &small(2)), Ok(101));
+        assert_eq!(packet_count(14, &small(16)), Ok(1));
+        assert_eq!(packet_count(15, &small(16)), Ok(2));
+        assert_eq!(packet_count(1, &small(1)), Err(SimpError::InvalidConfig));
+
+        let two_byte = ProtocolConfig {
+            sequence_width: SequenceWidth::Two,
+            ..Default::default()
+        };
+        assert_eq!(packet_count(247, &two_byte), Ok(1));
+        assert_eq!(packet_count(248, &two_byte), Ok(2));
+        let no_room = ProtocolConfig {
+            max_payload_size: 2,
+            ..two_byte
+        };
+        assert_eq!(packet_count(1, &no_room), Err(SimpError::InvalidConfig));
+
+        let with_marker = ProtocolConfig {
+            transfer_start: true,
+            ..Default::default()
+        };
+        assert_eq!(packet_count(0, &with_marker), Ok(2));
+    }
 
-            let result = receive_packet(&mut uart);
-            assert!(result.is_err());
-            assert_eq!(result.err().unwrap(), "Failed to receive packet");
+    #[test]
+    fn test_packet_count_matches_sent_packets() {
+        for config in [
+            ProtocolConfig::default(),
+            ProtocolConfig {
+                sequence_width: SequenceWidth::Two,
+                ..Default::default()
+            },
+            ProtocolConfig {
+                transfer_start: true,
+                ..Default::default()
+            },
+        ] {
+            for len in [0, 1, 248, 249, 250, 498, 800] {
+                let mut uart = MockUart::new();
+                let data = vec![0x02; len];
+                let expected_packets = packet_count(len, &config).unwrap();
+                uart.set_read_data(vec![ACK_BYTE; expected_packets]);
+
+                send_multiple_packets_with_config(
+                    &mut uart,
+                    &data,
+                    1,
+                    Duration::from_millis(50),
+                    &config,
+                )
+                .unwrap();
+
+                let sent_data = uart.get_written_data();
+                let sent_packets = sent_data
+                    .iter()
+                    .filter(|&&b| b == crate::packet::START_BYTE)
+                    .count();
+                assert_eq!(sent_packets, expected_packets);
+            }
         }
+    }
 
-        #[test]
-        fn test_send_multiple_packets_with_ack() {
-            let mut uart = MockUart::new();
-            let data = vec![0x02; 800]; // Data larger than 256 bytes
+    #[test]
+    fn test_multiple_packets_with_two_byte_sequence() {
+        let config = ProtocolConfig {
+            sequence_width: SequenceWidth::Two,
+            ..Default::default()
+        };
+        let chunk_size = MAX_PAYLOAD_SIZE - 2;
+
+        // 300 chunks, the last one short so it ends the transfer
+        let data = vec![0x00; 299 * chunk_size + 100];
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(vec![ACK_BYTE; 300]);
+        let result = send_multiple_packets_with_config(
+            &mut uart,
+            &data,
+            1,
+            Duration::from_millis(50),
+            &config,
+        );
+        assert!(result.is_ok());
+
+        // Packet 257 carries sequence 0x0101 rather than wrapping back to 1
+        let sent_data = uart.get_written_data();
+        let mut offset = 0;
+        for _ in 0..257 {
+            offset += Packet::frame_len(&sent_data[offset..], &config).unwrap();
+        }
+        assert_eq!(&sent_data[offset + 2..offset + 4], &[0x01, 0x01]);
+
+        uart.set_read_data(sent_data);
+        let received = receive_multiple_packets_with_config(&mut uart, &config).unwrap();
+        assert_eq!(received, data);
+    }
+
+    #[test]
+    fn test_send_packet_with_ack_nack_retries_immediately() {
+        let clock = MockClock::new();
+        let mut uart = MockUart::with_clock(&clock, Duration::from_millis(1));
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+        uart.set_read_data(vec![NACK_BYTE, NACK_BYTE, ACK_BYTE]);
+
+        let result = send_packet_with_ack_and_clock(
+            &mut uart,
+            &packet,
+            3,
+            Duration::from_millis(50),
+            Duration::from_millis(300),
+            &clock,
+        );
+        assert!(result.is_ok());
+
+        // Neither NACK waited for the timeout or the backoff, only one read per attempt
+        assert_eq!(clock.now(), Duration::from_millis(3));
+        assert_eq!(uart.get_written_data(), packet.to_bytes().repeat(3));
+    }
+
+    #[test]
+    fn test_send_packet_with_ack_timeout_backs_off() {
+        let clock = MockClock::new();
+        let mut uart = MockUart::with_clock(&clock, Duration::from_millis(10));
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+
+        let result = send_packet_with_ack_and_clock(
+            &mut uart,
+            &packet,
+            2,
+            Duration::from_millis(50),
+            Duration::from_millis(300),
+            &clock,
+        );
+        assert_eq!(result.err(), Some(SimpError::RetriesExhausted));
+
+        // Two timeouts with a single backoff between them
+        assert_eq!(clock.now(), Duration::from_millis(400));
+        assert_eq!(uart.get_written_data(), packet.to_bytes().repeat(2));
+    }
 
-            // Simulate an ACK for each packet sent
-            uart.set_read_data(vec![ACK_BYTE; 4]);
+    #[test]
+    fn test_receive_multiple_packets_to_writer() {
+        let data: Vec<u8> = (0..1000).map(|i| (i % 100) as u8).collect();
+        let packet_data = multi_packet_stream(&data, &ProtocolConfig::default());
 
-            let result =
-                send_multiple_packets_with_ack(&mut uart, &data, 3, Duration::from_millis(500));
-            assert!(result.is_ok());
+        let mut uart = MockUart::new();
+        uart.set_read_data(packet_data.clone());
+        let in_memory = receive_multiple_packets(&mut uart).unwrap();
 
-            // Extract sent data for debugging
-            let sent_data = uart.get_written_data();
-            println!("Sent Data: {:?}", sent_data);
+        uart.set_read_data(packet_data);
+        let mut writer = Vec::new();
+        let written = receive_multiple_packets_to_writer(&mut uart, &mut writer).unwrap();
 
-            // Define the expected number of packets (800 bytes, max 250 payload per packet, so 4 packets)
-            let max_payload_size = 250;
-            let mut expected_sequence = 0u8;
+        assert_eq!(written, data.len());
+        assert_eq!(writer, in_memory);
+        assert_eq!(writer, data);
+    }
+
+    #[test]
+    fn test_receive_packet_with_timeout_across_empty_reads() {
+        let clock = MockClock::new();
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+        let mut uart = MockUart::with_clock(&clock, Duration::from_millis(1));
+        uart.delay_reads(Duration::from_millis(2), Duration::from_millis(2));
+        uart.set_read_data(packet.to_bytes());
+
+        let received = receive_packet_with_timeout_and_clock(
+            &mut uart,
+            Duration::from_millis(100),
+            &ProtocolConfig::default(),
+            &clock,
+        )
+        .unwrap();
+        assert_eq!(received.payload, vec![0x01, 0x02, 0x03]);
+
+        // Without a timeout the first empty read ends the receive
+        uart.set_read_data(packet.to_bytes());
+        assert_eq!(receive_packet(&mut uart).err(), Some(SimpError::Timeout));
+    }
 
-            // Iterate over chunks of sent data, assuming each packet is prefixed with START_BYTE and ends with END_BYTE
-            let mut offset = 0;
-            while offset < sent_data.len() {
-                assert_eq!(sent_data[offset], crate::packet::START_BYTE); // Check start byte
-                offset += 1;
+    #[test]
+    fn test_receive_packet_with_timeout_expires() {
+        let clock = MockClock::new();
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+        let mut uart = MockUart::with_clock(&clock, Duration::from_millis(1));
+        uart.delay_reads(Duration::from_millis(2), Duration::from_millis(2));
+        uart.set_read_data(packet.to_bytes());
+
+        // The frame needs 14 reads, two per byte
+        let result = receive_packet_with_timeout_and_clock(
+            &mut uart,
+            Duration::from_millis(10),
+            &ProtocolConfig::default(),
+            &clock,
+        );
+        assert_eq!(result.err(), Some(SimpError::Timeout));
+        assert_eq!(clock.now(), Duration::from_millis(10));
+    }
 
-                let length = sent_data[offset] as usize; // Get the packet length
-                offset += 1;
+    #[test]
+    fn test_send_packet_with_ack_rtt() {
+        let clock = MockClock::new();
+        let mut uart = MockUart::with_clock(&clock, Duration::from_millis(1));
+        uart.delay_reads(Duration::from_millis(25), Duration::ZERO);
+        uart.set_read_data(vec![ACK_BYTE]);
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+
+        let rtt = send_packet_with_ack_rtt(
+            &mut uart,
+            &packet,
+            3,
+            Duration::from_millis(100),
+            Duration::ZERO,
+            &clock,
+        );
+        assert_eq!(rtt, Ok(Duration::from_millis(25)));
+
+        // An ACK slower than the timeout is never measured
+        uart.delay_reads(Duration::from_millis(150), Duration::ZERO);
+        uart.set_read_data(vec![ACK_BYTE]);
+        let rtt = send_packet_with_ack_rtt(
+            &mut uart,
+            &packet,
+            2,
+            Duration::from_millis(100),
+            Duration::ZERO,
+            &clock,
+        );
+        assert_eq!(rtt, Err(SimpError::RetriesExhausted));
+    }
 
-                assert_eq!(sent_data[offset], expected_sequence); // Check sequence number
-                offset += 1;
+    #[test]
+    fn test_send_packet_with_ack_mock_clock_timeout() {
+        let clock = MockClock::new();
+        let mut uart = MockUart::with_clock(&clock, Duration::from_millis(10));
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
 
-                // Calculate expected payload length
-                let payload_length = length - 1; // Length includes sequence byte but not checksum
+        let start_time = Instant::now();
+        let result = send_packet_with_ack_and_clock(
+            &mut uart,
+            &packet,
+            3,
+            Duration::from_secs(60),
+            Duration::from_secs(5),
+            &clock,
+        );
+        assert_eq!(result.err().unwrap(), SimpError::RetriesExhausted);
+        assert_eq!(uart.get_written_data(), packet.to_bytes().repeat(3));
+
+        // Three minute-long timeouts and two backoffs passed on the mock clock only
+        assert_eq!(clock.now(), Duration::from_secs(190));
+        assert!(start_time.elapsed() < Duration::from_secs(5));
+    }
 
-                // Verify payload bytes
-                let payload_end = offset + payload_length;
-                assert!(payload_end < sent_data.len());
+    #[test]
+    fn test_resend_request_rewinds_transfer() {
+        let data: Vec<u8> = (0..1100).map(|i| (i % 100) as u8).collect();
+        let config = ProtocolConfig::default();
+        let packets = multi_packet_frames(&data, &config);
+        assert_eq!(packets.len(), 5);
+
+        // The receiver lost packet 2 and asks for it while the sender waits on packet 3
+        let mut receiver = MockUart::new();
+        receiver.write(&[ACK_BYTE, ACK_BYTE, ACK_BYTE]).unwrap();
+        request_resend(&mut receiver, 2, &config).unwrap();
+        receiver.write(&[ACK_BYTE, ACK_BYTE, ACK_BYTE]).unwrap();
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(receiver.get_written_data());
+        let result = send_multiple_packets_with_config(
+            &mut uart,
+            &data,
+            1,
+            Duration::from_millis(50),
+            &config,
+        );
+        assert!(result.is_ok());
+
+        // Packets 0 to 3 went out once, then only packet 2 onward was resent
+        let expected_data = [0, 1, 2, 3, 2, 3, 4]
+            .iter()
+            .flat_map(|&index| packets[index].clone())
+            .collect::<Vec<u8>>();
+        assert_eq!(uart.get_written_data(), expected_data);
+    }
 
-                let payload = &sent_data[offset..payload_end];
-                let expected_payload_start = (expected_sequence as usize) * (max_payload_size - 1);
-                let expected_payload_end = expected_payload_start + payload.len();
-                let expected_payload = &data[expected_payload_start..expected_payload_end];
-                assert_eq!(payload, expected_payload);
+    #[test]
+    fn test_resend_request_for_future_packet() {
+        let data = vec![0x01; 300];
+        let config = ProtocolConfig::default();
+
+        // Asking for a packet that was never sent is rejected
+        let mut receiver = MockUart::new();
+        request_resend(&mut receiver, 1, &config).unwrap();
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(receiver.get_written_data());
+        let result = resume_multiple_packets_with_ack(
+            &mut uart,
+            &data,
+            0,
+            1,
+            Duration::from_millis(50),
+            &config,
+        );
+        let partial = result.err().unwrap();
+        assert_eq!(partial.delivered, 0);
+        assert_eq!(partial.error, SimpError::InvalidResendRequest);
+    }
 
-                offset = payload_end;
+    #[test]
+    fn test_receive_multiple_packets_max_packets() {
+        // A sender that keeps sending full packets and never ends the transfer
+        let config = ProtocolConfig {
+            max_packets: Some(3),
+            ..Default::default()
+        };
+        let data = vec![0x01; 5 * (MAX_PAYLOAD_SIZE - 1)];
+        let packet_data = multi_packet_frames(&data, &config)[..5].concat();
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(packet_data);
+
+        let mut received = Vec::new();
+        let mut received_packets = 0;
+        let result = resume_receive_multiple_packets(
+            &mut uart,
+            &mut received,
+            &mut received_packets,
+            &config,
+        );
+        assert_eq!(result.err().unwrap(), SimpError::TooManyPackets);
+        assert_eq!(received_packets, 3);
+        assert_eq!(received.len(), 3 * (MAX_PAYLOAD_SIZE - 1));
+    }
 
-                // Verify checksum
-                let checksum_start = offset - payload_length - 1; // sequence byte + payload
-                let checksum_data = &sent_data[checksum_start..payload_end];
-                let calculated_checksum = Packet::calculate_checksum(checksum_data);
-                let actual_checksum = sent_data[offset];
-                assert_eq!(actual_checksum, calculated_checksum);
-                offset += 1;
+    #[test]
+    fn test_termination_short_packet() {
+        let data = vec![0x01; 600];
+        let packet_data = multi_packet_stream(&data, &ProtocolConfig::default());
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(packet_data);
+        let (received, termination) =
+            receive_multiple_packets_with_termination(&mut uart, &ProtocolConfig::default())
+                .unwrap();
+        assert_eq!(termination, Termination::ShortPacket);
+        assert_eq!(received, data);
+    }
 
-                assert_eq!(sent_data[offset], crate::packet::END_BYTE); // Check end byte
-                offset += 1;
+    #[test]
+    fn test_termination_packet_limit() {
+        let config = ProtocolConfig {
+            max_packets: Some(2),
+            ..Default::default()
+        };
+        let data = vec![0x01; 3 * (MAX_PAYLOAD_SIZE - 1)];
+        let packet_data = multi_packet_frames(&data, &config)[..3].concat();
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(packet_data);
+        let (received, termination) =
+            receive_multiple_packets_with_termination(&mut uart, &config).unwrap();
+        assert_eq!(termination, Termination::PacketLimit);
+        assert_eq!(received.len(), 2 * (MAX_PAYLOAD_SIZE - 1));
+    }
 
-                // Increment sequence number, wrapping on overflow
-                expected_sequence = expected_sequence.wrapping_add(1);
+    #[test]
+    fn test_receive_multiple_packets_with_keepalives() {
+        let data = vec![0x01; 600];
+        for config in [
+            ProtocolConfig::default(),
+            ProtocolConfig {
+                start_byte: 0x02,
+                end_byte: 0x03,
+                checksum_kind: crate::packet::ChecksumKind::Crc16,
+                ..Default::default()
+            },
+            ProtocolConfig {
+                framing: Framing::LengthPrefixed,
+                ..Default::default()
+            },
+        ] {
+            let mut sender = MockUart::new();
+            send_keepalive(&mut sender, &config).unwrap();
+            for frame in multi_packet_frames(&data, &config) {
+                sender.write(&frame).unwrap();
+                send_keepalive(&mut sender, &config).unwrap();
+                send_keepalive(&mut sender, &config).unwrap();
             }
 
-            // Ensure we processed the correct number of packets
-            assert_eq!(expected_sequence, 4); // Should have sent 4 packets
+            let mut uart = MockUart::new();
+            uart.set_read_data(sender.get_written_data());
+            let (received, termination) =
+                receive_multiple_packets_with_termination(&mut uart, &config).unwrap();
+            assert_eq!(received, data);
+            assert_eq!(termination, Termination::ShortPacket);
         }
+    }
 
-        #[test]
-        fn test_receive_multiple_packets() {
-            let mut uart = MockUart::new();
-            let data = vec![0x01; 600]; // Data larger than 256 bytes
-
-            // Create packets with sequence numbers and set to mock UART
-            let mut packet_data = Vec::new();
-            let mut sequence = 0u8;
-            for chunk in data.chunks(250) {
-                let mut chunk_with_seq = vec![sequence];
-                chunk_with_seq.extend_from_slice(chunk);
-                let packet = Packet::new(chunk_with_seq);
-                packet_data.extend(packet.to_bytes());
-                sequence = sequence.wrapping_add(1);
-            }
-            uart.set_read_data(packet_data);
+    #[test]
+    fn test_receive_packet_max_frame_size() {
+        let mut uart = MockUart::new();
+        // A babbling line that never sends the end byte
+        let mut stream = vec![crate::packet::START_BYTE];
+        stream.extend(vec![0x55; 1000]);
+        uart.set_read_data(stream);
+
+        let config = ProtocolConfig {
+            max_frame_size: Some(64),
+            ..Default::default()
+        };
+        let result = receive_packet_with_config(&mut uart, &config);
+        assert!(matches!(result, Err(SimpError::FrameTooLarge)));
+        // The receive stopped at the bound instead of draining the line
+        assert!(uart.read().is_some());
+    }
+
+    #[test]
+    fn test_receive_packet_within_max_frame_size() {
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+        let bytes = packet.to_bytes();
+        let mut uart = MockUart::new();
+        uart.set_read_data(bytes.clone());
+
+        let config = ProtocolConfig {
+            max_frame_size: Some(bytes.len()),
+            ..Default::default()
+        };
+        let received = receive_packet_with_config(&mut uart, &config).unwrap();
+        assert_eq!(received.payload, packet.payload);
+    }
+
+    #[test]
+    fn test_receive_multiple_packets_with_crc32() {
+        let data: Vec<u8> = (0..1000).map(|i| (i * 31 % 256) as u8).collect();
+        let mut sender = MockUart::new();
+        sender.set_read_data(vec![ACK_BYTE; 5]);
+        send_multiple_packets_with_ack(&mut sender, &data, 1, Duration::from_millis(50)).unwrap();
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(sender.get_written_data());
+        let (received, crc) =
+            receive_multiple_packets_with_crc32(&mut uart, &ProtocolConfig::default()).unwrap();
+        assert_eq!(received, data);
+        assert_eq!(crc, Packet::calculate_crc32(&data));
+    }
 
-            let result = receive_multiple_packets(&mut uart);
-            assert!(result.is_ok());
+    #[test]
+    fn test_receive_multiple_packets_array() {
+        let data: Vec<u8> = (0..600).map(|i| (i % 100) as u8).collect();
+        let mut sender = MockUart::new();
+        sender.set_read_data(vec![ACK_BYTE; 3]);
+        send_multiple_packets_with_ack(&mut sender, &data, 1, Duration::from_millis(50)).unwrap();
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(sender.get_written_data());
+        let (received, len) = receive_multiple_packets_array::<1024>(&mut uart).unwrap();
+        assert_eq!(len, data.len());
+        assert_eq!(&received[..len], data.as_slice());
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(sender.get_written_data());
+        let result = receive_multiple_packets_array::<512>(&mut uart);
+        assert_eq!(result.err(), Some(SimpError::BufferFull));
+    }
+
+    #[test]
+    fn test_receive_multiple_packets_into_slice_buffer() {
+        let data = vec![0x01; 600];
+        let config = ProtocolConfig::default();
+        let stream = multi_packet_stream(&data, &config);
+
+        let mut storage = [0u8; 600];
+        let mut buffer = SliceBuffer::new(&mut storage);
+        let mut uart = MockUart::new();
+        uart.set_read_data(stream.clone());
+        receive_multiple_packets_into(&mut uart, &mut buffer, &config).unwrap();
+        assert_eq!(buffer.as_slice(), data.as_slice());
+
+        // One byte short, the final chunk overflows
+        let mut storage = [0u8; 599];
+        let mut buffer = SliceBuffer::new(&mut storage);
+        let mut uart = MockUart::new();
+        uart.set_read_data(stream.clone());
+        let result = receive_multiple_packets_into(&mut uart, &mut buffer, &config);
+        assert!(matches!(result, Err(SimpError::BufferFull)));
+        assert_eq!(buffer.len(), 2 * (MAX_PAYLOAD_SIZE - 1));
+    }
 
-            let received_data = result.unwrap();
-            assert_eq!(received_data, data);
+    #[test]
+    fn test_send_packet_with_ack_and_deadline() {
+        let clock = MockClock::new();
+        let mut uart = MockUart::with_clock(&clock, Duration::from_millis(10));
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+
+        let result = send_packet_with_ack_and_deadline(
+            &mut uart,
+            &packet,
+            100,
+            Duration::from_millis(100),
+            Duration::from_secs(1),
+            Duration::ZERO,
+            &clock,
+        );
+        assert_eq!(result.err().unwrap(), SimpError::DeadlineExceeded);
+
+        // Each attempt gave up after 100ms, and the deadline stopped the retries
+        assert_eq!(uart.get_written_data(), packet.to_bytes().repeat(10));
+        assert_eq!(clock.now(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_send_packet_with_ack_and_deadline_caps_last_attempt() {
+        let clock = MockClock::new();
+        let mut uart = MockUart::with_clock(&clock, Duration::from_millis(10));
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+
+        let result = send_packet_with_ack_and_deadline(
+            &mut uart,
+            &packet,
+            100,
+            Duration::from_millis(400),
+            Duration::from_secs(1),
+            Duration::from_millis(100),
+            &clock,
+        );
+        assert_eq!(result.err().unwrap(), SimpError::DeadlineExceeded);
+
+        // 400ms attempt, 100ms backoff, 400ms attempt, 100ms backoff, then the deadline
+        assert_eq!(uart.get_written_data(), packet.to_bytes().repeat(2));
+        assert_eq!(clock.now(), Duration::from_secs(1));
+    }
+
+    struct MockReader(VecDeque<u8>);
+
+    impl Reader for MockReader {
+        fn read(&mut self) -> Option<u8> {
+            self.0.pop_front()
         }
     }
+
+    struct MockWriter(Vec<u8>);
+
+    impl Writer for MockWriter {
+        fn write(&mut self, data: &[u8]) -> Result<usize, SimpError> {
+            self.0.extend_from_slice(data);
+            Ok(data.len())
+        }
+    }
+
+    #[test]
+    fn test_split_reader_and_writer() {
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+        let mut reader = MockReader(VecDeque::from(vec![ACK_BYTE]));
+        let mut writer = MockWriter(Vec::new());
+
+        let mut uart = SplitUart::new(&mut reader, &mut writer);
+        send_packet_with_ack(&mut uart, &packet, 3, Duration::from_millis(100)).unwrap();
+        assert_eq!(writer.0, packet.to_bytes());
+
+        // Each half works on its own
+        let mut reader = MockReader(writer.0.drain(..).collect());
+        let received = receive_packet_from_reader(&mut reader, &ProtocolConfig::default());
+        assert_eq!(received.unwrap().payload, packet.payload);
+        send_packet_to_writer(&mut writer, &packet).unwrap();
+        assert_eq!(writer.0, packet.to_bytes());
+    }
+
+    #[test]
+    fn test_corrupted_sequence_number_is_a_checksum_error() {
+        let first = Packet::new([vec![0x00], vec![0x01; MAX_PAYLOAD_SIZE - 1]].concat());
+        let second = Packet::new(vec![0x01, 0x02, 0x03]);
+        let mut stream = first.to_bytes();
+        let second_start = stream.len();
+        stream.extend(second.to_bytes());
+        // Flip a bit of the second packet's sequence number, turning it into a repeat of 0
+        stream[second_start + 2] ^= 0x01;
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(stream);
+        let result = receive_multiple_packets(&mut uart);
+        assert!(matches!(result, Err(SimpError::ChecksumMismatch)));
+    }
+
+    #[test]
+    fn test_receive_packet_with_ack() {
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+        let mut uart = MockUart::new();
+        uart.set_read_data(packet.to_bytes());
+
+        let (result, reply) = receive_packet_with_ack(&mut uart);
+        assert_eq!(result.unwrap().payload, packet.payload);
+        assert_eq!(reply, Ok(Some(ACK_BYTE)));
+        assert_eq!(uart.get_written_data(), vec![ACK_BYTE]);
+    }
+
+    #[test]
+    fn test_receive_packet_with_ack_only_nacks_corrupt_frames() {
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+        let mut uart = MockUart::new();
+        uart.set_read_data(packet.to_bytes());
+        assert_eq!(receive_packet_with_ack(&mut uart).1, Ok(Some(ACK_BYTE)));
+
+        // A device error is not the sender's fault, so it is not NACKed
+        uart.fail_reads("Device unplugged");
+        let (result, reply) = receive_packet_with_ack(&mut uart);
+        assert_eq!(result.err(), Some(SimpError::Uart("Device unplugged")));
+        assert_eq!(reply, Ok(None));
+
+        // Neither is a frame that outgrows max_frame_size
+        let config = ProtocolConfig {
+            max_frame_size: Some(4),
+            ..Default::default()
+        };
+        uart.set_read_data(packet.to_bytes());
+        let (result, reply) = receive_packet_with_ack_and_config(&mut uart, &config);
+        assert_eq!(result.err(), Some(SimpError::FrameTooLarge));
+        assert_eq!(reply, Ok(None));
+        assert_eq!(uart.get_written_data(), vec![ACK_BYTE]);
+    }
+
+    #[test]
+    fn test_receive_packet_with_ack_keeps_packet_when_reply_fails() {
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+        let mut uart = MockUart::new();
+        uart.set_read_data(packet.to_bytes());
+        uart.fail_next_write(1);
+
+        let (result, reply) = receive_packet_with_ack(&mut uart);
+        assert_eq!(result.unwrap().payload, packet.payload);
+        assert_eq!(reply, Err(SimpError::ReplyFailed));
+
+        // The NACK failing does not hide why the frame was rejected
+        let mut bytes = packet.to_bytes();
+        bytes[4] ^= 0x01;
+        uart.set_read_data(bytes);
+        uart.fail_next_write(1);
+        let (result, reply) = receive_packet_with_ack(&mut uart);
+        assert_eq!(result.err(), Some(SimpError::ChecksumMismatch));
+        assert_eq!(reply, Err(SimpError::ReplyFailed));
+    }
+
+    #[test]
+    fn test_receive_packet_with_ack_corrupt_frame() {
+        let mut bytes = Packet::new(vec![0x01, 0x02, 0x03]).to_bytes();
+        bytes[4] ^= 0x01;
+        let mut uart = MockUart::new();
+        uart.set_read_data(bytes);
+
+        let (result, reply) = receive_packet_with_ack(&mut uart);
+        assert!(matches!(result, Err(SimpError::ChecksumMismatch)));
+        assert_eq!(reply, Ok(Some(NACK_BYTE)));
+        assert_eq!(uart.get_written_data(), vec![NACK_BYTE]);
+
+        // Nothing arrived, so nothing is sent
+        let (result, reply) = receive_packet_with_ack(&mut uart);
+        assert!(result.is_err());
+        assert_eq!(reply, Ok(None));
+    }
+
+    #[test]
+    fn test_transfer_start_marker() {
+        let data = vec![0x01; 600];
+        let config = ProtocolConfig {
+            transfer_start: true,
+            ..Default::default()
+        };
+
+        let mut sender = MockUart::new();
+        sender.set_read_data(vec![ACK_BYTE; 4]);
+        send_multiple_packets_with_config(
+            &mut sender,
+            &data,
+            3,
+            Duration::from_millis(100),
+            &config,
+        )
+        .unwrap();
+        let sent = sender.get_written_data();
+        let marker = Packet::new(TRANSFER_START.to_vec()).to_bytes();
+        assert_eq!(sent[..marker.len()], marker[..]);
+
+        // Frames left over from an aborted transfer precede the real one
+        let mut stream = Vec::new();
+        for index in 1..3u8 {
+            stream.extend(Packet::new(vec![index, 0x55, 0x55]).to_bytes());
+        }
+        stream.extend(sent);
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(stream);
+        let received = receive_multiple_packets_with_config(&mut uart, &config).unwrap();
+        assert_eq!(received, data);
+    }
+
+    #[test]
+    fn test_stx_etx_framing_end_to_end() {
+        let config = ProtocolConfig {
+            start_byte: 0x02,
+            end_byte: 0x03,
+            ..Default::default()
+        };
+        // Includes the framing bytes and the default START_BYTE and END_BYTE
+        let mut data = vec![0x41; 400];
+        data[10..14].copy_from_slice(&[0x02, 0x03, 0x7E, 0x7F]);
+        data[300..304].copy_from_slice(&[0x02, 0x03, 0x7E, 0x7F]);
+
+        let mut sender = MockUart::new();
+        sender.set_read_data(vec![ACK_BYTE; 2]);
+        send_multiple_packets_with_config(
+            &mut sender,
+            &data,
+            3,
+            Duration::from_millis(100),
+            &config,
+        )
+        .unwrap();
+        let sent = sender.get_written_data();
+        assert_eq!(sent[0], 0x02);
+        assert_eq!(sent[sent.len() - 1], 0x03);
+        assert_eq!(sent.iter().filter(|&&b| b == 0x02).count(), 2);
+
+        let report = crate::diagnostics::FramingReport::analyze_with_config(&sent, &config);
+        assert_eq!(report.valid_frames, 2);
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(sent);
+        let received = receive_multiple_packets_with_config(&mut uart, &config).unwrap();
+        assert_eq!(received, data);
+    }
+
+    #[test]
+    fn test_inter_packet_delay() {
+        let data = vec![0x01; 600];
+        let config = ProtocolConfig {
+            inter_packet_delay: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let clock = MockClock::new();
+        let mut uart = MockUart::new();
+        uart.set_read_data(vec![ACK_BYTE; 3]);
+
+        resume_multiple_packets_with_clock(
+            &mut uart,
+            &data,
+            0,
+            3,
+            Duration::from_millis(100),
+            &config,
+            &clock,
+        )
+        .unwrap();
+
+        // Three packets, so two delays between them and none after the last
+        assert_eq!(clock.now(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_read_error_propagates() {
+        let mut uart = MockUart::new();
+        let bytes = Packet::new(vec![0x01, 0x02, 0x03]).to_bytes();
+        uart.set_read_data(bytes[..3].to_vec());
+        uart.fail_reads("Device unplugged");
+
+        assert!(matches!(
+            receive_packet(&mut uart),
+            Err(SimpError::Uart("Device unplugged"))
+        ));
+        assert!(matches!(
+            receive_packet_by_length(&mut uart),
+            Err(SimpError::Uart("Device unplugged"))
+        ));
+        let packet = Packet::new(vec![0x01]);
+        let result = send_packet_with_ack(&mut uart, &packet, 3, Duration::from_millis(100));
+        assert!(matches!(result, Err(SimpError::Uart("Device unplugged"))));
+    }
+
+    #[test]
+    fn test_send_multiple_packets_escaped_chunk_too_large() {
+        // Every byte doubles under escaping, so the first chunk can't fit in a frame
+        let data = vec![crate::packet::START_BYTE; 300];
+        let mut uart = MockUart::new();
+        uart.set_read_data(vec![ACK_BYTE; 2]);
+
+        let result = resume_multiple_packets_with_ack(
+            &mut uart,
+            &data,
+            0,
+            3,
+            Duration::from_millis(100),
+            &ProtocolConfig::default(),
+        );
+        assert_eq!(
+            result,
+            Err(PartialTransfer {
+                delivered: 0,
+                error: SimpError::PayloadTooLarge,
+            })
+        );
+        assert!(uart.get_written_data().is_empty());
+    }
+
+    #[test]
+    fn test_send_packet_with_retry_policy() {
+        let clock = MockClock::new();
+        let mut uart = MockUart::with_clock(&clock, Duration::from_millis(10));
+        let packet = Packet::new(vec![0x01, 0x02, 0x03]);
+        let mut policy = crate::retry::ExponentialBackoff {
+            retries: 2,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(10),
+        };
+
+        let result = send_packet_with_retry_policy(
+            &mut uart,
+            &packet,
+            Duration::from_secs(5),
+            &mut policy,
+            &clock,
+        );
+        assert_eq!(result.err().unwrap(), SimpError::RetriesExhausted);
+        assert_eq!(uart.get_written_data(), packet.to_bytes().repeat(3));
+        // Three timeouts, then delays of 1s and 2s between them
+        assert_eq!(clock.now(), Duration::from_secs(18));
+    }
+
+    #[test]
+    fn test_stale_ack_does_not_confirm_sequenced_send() {
+        let config = ProtocolConfig::default();
+        let clock = MockClock::new();
+        let mut uart = MockUart::with_clock(&clock, Duration::from_millis(1));
+        // ACK left over from the previous send with sequence 4
+        uart.set_read_data(vec![ACK_BYTE, 0x04]);
+
+        let result = send_packet_with_sequenced_ack_and_clock(
+            &mut uart,
+            &[0x01, 0x02],
+            5,
+            2,
+            Duration::from_millis(10),
+            &config,
+            &clock,
+        );
+        assert_eq!(result.err().unwrap(), SimpError::RetriesExhausted);
+        assert_eq!(clock.now(), Duration::from_millis(20));
+
+        uart.set_read_data(vec![ACK_BYTE, 0x04, ACK_BYTE, 0x05]);
+        let result = send_packet_with_sequenced_ack_and_clock(
+            &mut uart,
+            &[0x01, 0x02],
+            5,
+            2,
+            Duration::from_millis(10),
+            &config,
+            &clock,
+        );
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_sequenced_ack_with_two_byte_sequence() {
+        let config = ProtocolConfig {
+            sequence_width: SequenceWidth::Two,
+            ..Default::default()
+        };
+        let clock = MockClock::new();
+        let mut uart = MockUart::with_clock(&clock, Duration::from_millis(1));
+        // An ACK for 0x0005 only matches in its low byte
+        uart.set_read_data(vec![ACK_BYTE, 0x05, 0x00, ACK_BYTE, 0x05, 0x01]);
+
+        let result = send_packet_with_sequenced_ack_and_clock(
+            &mut uart,
+            &[0x01, 0x02],
+            0x0105,
+            1,
+            Duration::from_millis(10),
+            &config,
+            &clock,
+        );
+        assert!(result.is_ok());
+        assert_eq!(
+            uart.get_written_data(),
+            Packet::new_with_config(vec![0x05, 0x01, 0x01, 0x02], &config).to_bytes()
+        );
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(
+            Packet::new_with_config(vec![0x05, 0x01, 0x01, 0x02], &config).to_bytes(),
+        );
+        let (sequence, payload) = receive_packet_with_sequenced_ack(&mut uart, &config).unwrap();
+        assert_eq!(sequence, 0x0105);
+        assert_eq!(payload, vec![0x01, 0x02]);
+        assert_eq!(uart.get_written_data(), vec![ACK_BYTE, 0x05, 0x01]);
+    }
+
+    #[test]
+    fn test_receive_packet_with_sequenced_ack() {
+        let config = ProtocolConfig::default();
+        let mut uart = MockUart::new();
+        uart.set_read_data(Packet::new(vec![0x05, 0x01, 0x02]).to_bytes());
+
+        let (sequence, payload) = receive_packet_with_sequenced_ack(&mut uart, &config).unwrap();
+        assert_eq!(sequence, 0x05);
+        assert_eq!(payload, vec![0x01, 0x02]);
+        assert_eq!(uart.get_written_data(), vec![ACK_BYTE, 0x05]);
+    }
+
+    #[test]
+    fn test_receive_packet_with_crc16() {
+        let config = ProtocolConfig {
+            checksum_kind: crate::packet::ChecksumKind::Crc16,
+            ..Default::default()
+        };
+        let packet = Packet::new_with_config(vec![0x01, 0x02, 0x03], &config);
+        assert_eq!(
+            roundtrip_with_config(&[0x01, 0x02, 0x03], &config).unwrap(),
+            vec![0x01, 0x02, 0x03]
+        );
+
+        let mut uart = MockUart::new();
+        uart.set_read_data(packet.to_bytes());
+        let received = receive_packet_by_length_with_config(&mut uart, &config).unwrap();
+        assert_eq!(received.checksum, packet.checksum);
+    }
 }